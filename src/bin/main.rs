@@ -5,37 +5,80 @@ extern crate clap;
 extern crate plonkit;
 
 use clap::Clap;
-use franklin_crypto::bellman::EncodedPoint;
-use franklin_crypto::bellman::bn256::{G1Uncompressed, G2Uncompressed};
-use std::fs::File;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
 use std::path::Path;
 use std::str;
+use std::str::FromStr;
+use std::time::Instant;
 
-use plonkit::bellman_ce::pairing::bn256::Bn256;
+use plonkit::bellman_ce::pairing::{bls12_381::Bls12, bn256::Bn256, ff::PrimeField};
+use plonkit::bellman_ce::plonk::better_cs::{
+    cs::PlonkCsWidth4WithNextStepParams,
+    keys::{Proof, VerificationKey},
+};
+use plonkit::bellman_ce::SynthesisError;
 
-use plonkit::circom_circuit::CircomCircuit;
-use plonkit::plonk;
+use plonkit::circom_circuit::{self, CircomCircuit};
+use plonkit::curve::{CircomEngine, Curve};
+use plonkit::groth16;
+use plonkit::invalid_input;
+use plonkit::plonk::{self, PlonkEngine};
 use plonkit::reader;
 use plonkit::recursive;
-use plonkit::bellman_ce::to_hex;
-use plonkit::{ ProofStr, VerificationKeyStr };
+use plonkit::server;
+use plonkit::symbols::CircuitSymbols;
+use plonkit::underconstrained;
+use plonkit::utils::{repr_to_big, write_atomically, write_file_atomically};
+use plonkit::witness;
+use plonkit::{ ArtifactMetaStr, Backend, Groth16ProofStr, ProofStr, VerificationKeyStr };
 
 /// A zkSNARK toolkit to work with circom zkSNARKs DSL in plonk proof system
 #[derive(Clap)]
-#[clap(version = "0.1.6")]
+#[clap(version = "0.1.6", after_help = EXIT_CODES_HELP)]
 struct Opts {
+    /// Format of the command result printed to stdout, text or json
+    #[clap(long = "output-format", default_value = "text", global = true)]
+    output_format: OutputFormat,
     #[clap(subcommand)]
     command: SubCommand,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    // results are only logged
+    Text,
+    // one json object describing the result is printed to stdout
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => anyhow::bail!(invalid_input!("unknown output format {}, expected text or json", s)),
+        }
+    }
+}
+
 #[derive(Clap)]
 enum SubCommand {
     /// Analyse the circuit and output some stats
     Analyse(AnalyseOpts),
-    /// Trusted locally set up Plonk universal srs in monomial form
+    /// Trusted locally set up Plonk universal srs in monomial form, or groth16 parameters of a circuit
     Setup(SetupOpts),
+    /// Import a snarkjs Powers of Tau file as Plonk universal srs in monomial form
+    ImportPtau(ImportPtauOpts),
     /// Dump "SRS in lagrange form" from a "SRS in monomial form"
     DumpLagrange(DumpLagrangeOpts),
+    /// Check a witness against the circuit constraints, and report the unsatisfied ones
+    CheckWitness(CheckWitnessOpts),
+    /// Look for underconstrained signals in the circuit
+    CheckUnderconstrained(CheckUnderconstrainedOpts),
     /// Generate a SNARK proof
     Prove(ProveOpts),
     /// Verify a SNARK proof
@@ -46,6 +89,8 @@ enum SubCommand {
     GenerateRecursiveVerifier(GenerateRecursiveVerifierOpts),
     /// Export verifying key
     ExportVerificationKey(ExportVerificationKeyOpts),
+    /// Export proving key
+    ExportProvingKey(ExportProvingKeyOpts),
     /// Export Recursive verifying key
     ExportRecursiveVerificationKey(ExportRecursiveVerificationKeyOpts),
     /// Aggregate multiple proofs
@@ -54,6 +99,32 @@ enum SubCommand {
     RecursiveVerify(RecursiveVerifyOpts),
     /// Check proofs aggregation
     CheckAggregation(CheckAggregationOpts),
+    /// Serve prove, verify and analyse over HTTP, keeping the circuit setups in memory
+    Serve(ServeOpts),
+}
+
+impl SubCommand {
+    fn name(&self) -> &'static str {
+        match self {
+            SubCommand::Analyse(_) => "analyse",
+            SubCommand::Setup(_) => "setup",
+            SubCommand::ImportPtau(_) => "import-ptau",
+            SubCommand::DumpLagrange(_) => "dump-lagrange",
+            SubCommand::CheckWitness(_) => "check-witness",
+            SubCommand::CheckUnderconstrained(_) => "check-underconstrained",
+            SubCommand::Prove(_) => "prove",
+            SubCommand::Verify(_) => "verify",
+            SubCommand::GenerateVerifier(_) => "generate-verifier",
+            SubCommand::GenerateRecursiveVerifier(_) => "generate-recursive-verifier",
+            SubCommand::ExportVerificationKey(_) => "export-verification-key",
+            SubCommand::ExportProvingKey(_) => "export-proving-key",
+            SubCommand::ExportRecursiveVerificationKey(_) => "export-recursive-verification-key",
+            SubCommand::RecursiveProve(_) => "recursive-prove",
+            SubCommand::RecursiveVerify(_) => "recursive-verify",
+            SubCommand::CheckAggregation(_) => "check-aggregation",
+            SubCommand::Serve(_) => "serve",
+        }
+    }
 }
 
 /// A subcommand for analysing the circuit and outputting some stats
@@ -65,14 +136,48 @@ struct AnalyseOpts {
     /// Output file
     #[clap(short = "o", long = "output", default_value = "analyse.json")]
     output: String,
+    /// Circuit symbols file, to name the constraints [default: the circuit file with a .sym extension, if it exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Output file for the gates of each component in folded stack format, which needs the circuit symbols
+    #[clap(short = "f", long = "folded")]
+    folded: Option<String>,
 }
 
-/// A subcommand for locally trusted setting up Plonk universal srs in monomial form
+/// A subcommand for locally trusted setting up Plonk universal srs in monomial form, or circuit specific groth16 parameters
 #[derive(Clap)]
 struct SetupOpts {
-    /// Power_of_two exponent
+    /// Proof system, plonk or groth16
+    #[clap(long = "backend", default_value = "plonk")]
+    backend: Backend,
+    /// Power_of_two exponent, required by plonk
     #[clap(short = "p", long = "power")]
-    power: u32,
+    power: Option<u32>,
+    /// Curve of the plonk srs, bn256 or bls12_381. groth16 parameters take the curve of the circuit
+    #[clap(long = "curve", default_value = "bn256")]
+    curve: Curve,
+    /// Output file for Plonk universal setup srs in monomial form, required by plonk
+    #[clap(short = "m", long = "srs_monomial_form")]
+    srs_monomial_form: Option<String>,
+    /// Circuit R1CS or JSON file for the groth16 setup [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Output file for groth16 parameters
+    #[clap(long = "params", default_value = "groth16_params.bin")]
+    params: String,
+    #[clap(long = "overwrite")]
+    overwrite: bool,
+}
+
+/// A subcommand for importing a Powers of Tau file as Plonk universal srs in monomial form
+#[derive(Clap)]
+struct ImportPtauOpts {
+    /// Source Powers of Tau file generated by snarkjs
+    #[clap(long = "ptau")]
+    ptau: String,
+    /// Power_of_two exponent [default: the power of the ptau file]
+    #[clap(short = "p", long = "power")]
+    power: Option<u32>,
     /// Output file for Plonk universal setup srs in monomial form
     #[clap(short = "m", long = "srs_monomial_form")]
     srs_monomial_form: String,
@@ -96,12 +201,46 @@ struct DumpLagrangeOpts {
     overwrite: bool,
 }
 
+/// A subcommand for checking a witness against the circuit constraints
+#[derive(Clap)]
+struct CheckWitnessOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Witness BIN or JSON file
+    #[clap(short = "w", long = "witness", default_value = "witness.wtns")]
+    witness: String,
+    /// Circuit symbols file, to name the signals [default: the circuit file with a .sym extension, if it exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+}
+
+/// A subcommand for looking for underconstrained signals in the circuit
+#[derive(Clap)]
+struct CheckUnderconstrainedOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Output file
+    #[clap(short = "o", long = "output", default_value = "underconstrained.json")]
+    output: String,
+    /// Circuit symbols file, to name the signals [default: the circuit file with a .sym extension, if it exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+}
+
 /// A subcommand for generating a SNARK proof
 #[derive(Clap)]
 struct ProveOpts {
-    /// Source file for Plonk universal setup srs in monomial form
+    /// Proof system, plonk or groth16
+    #[clap(long = "backend", default_value = "plonk")]
+    backend: Backend,
+    /// Source file for Plonk universal setup srs in monomial form, required by plonk
     #[clap(short = "m", long = "srs_monomial_form")]
-    srs_monomial_form: String,
+    srs_monomial_form: Option<String>,
+    /// Source file for groth16 parameters
+    #[clap(long = "params", default_value = "groth16_params.bin")]
+    params: String,
     /// Source file for Plonk universal setup srs in lagrange form
     #[clap(short = "l", long = "srs_lagrange_form")]
     srs_lagrange_form: Option<String>,
@@ -111,6 +250,15 @@ struct ProveOpts {
     /// Witness BIN or JSON file
     #[clap(short = "w", long = "witness", default_value = "witness.wtns")]
     witness: String,
+    /// Circuit input JSON file, to calculate the witness with --wasm instead of reading --witness
+    #[clap(long = "input")]
+    input: Option<String>,
+    /// Circuit WASM file generated by circom 2.x, used together with --input
+    #[clap(long = "wasm")]
+    wasm: Option<String>,
+    /// Proving key file generated by export-proving-key, skipping the circuit setup
+    #[clap(short = "k", long = "proving_key")]
+    proving_key: Option<String>,
     /// Output file for proof BIN
     #[clap(short = "p", long = "proof", default_value = "proof.bin")]
     proof: String,
@@ -120,8 +268,15 @@ struct ProveOpts {
     /// Output file for public input json
     #[clap(short = "i", long = "publicjson", default_value = "public.json")]
     publicjson: String,
+    /// Output file for public input json keyed by signal names, which needs the circuit symbols
+    #[clap(long = "named_publicjson")]
+    named_publicjson: Option<String>,
+    /// Circuit symbols file [default: the circuit file with a .sym extension]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Transcript, keccak or rescue
     #[clap(short = "t", long = "transcript", default_value = "keccak")]
-    transcript: String,
+    transcript: plonk::Transcript,
     #[clap(long = "overwrite")]
     overwrite: bool,
 }
@@ -129,14 +284,27 @@ struct ProveOpts {
 /// A subcommand for verifying a SNARK proof
 #[derive(Clap)]
 struct VerifyOpts {
-    /// Proof BIN file
+    /// Proof system, plonk or groth16
+    #[clap(long = "backend", default_value = "plonk")]
+    backend: Backend,
+    /// Proof BIN or JSON file
     #[clap(short = "p", long = "proof", default_value = "proof.bin")]
     proof: String,
-    /// Verification key file
+    /// Proof file list text file, to verify many proofs in a batch instead of --proof
+    #[clap(short = "f", long = "proof_list")]
+    proof_list: Option<String>,
+    /// Verification key BIN or JSON file
     #[clap(short = "v", long = "verification_key", default_value = "vk.bin")]
     vk: String,
-    #[clap(short = "t", long = "transcript", default_value = "keccak")]
-    transcript: String,
+    /// Transcript, keccak or rescue [default: the transcript recorded in the proof, or keccak]
+    #[clap(short = "t", long = "transcript")]
+    transcript: Option<plonk::Transcript>,
+    /// Public input json file, groth16 proofs don't embed their inputs
+    #[clap(short = "i", long = "publicjson", default_value = "public.json")]
+    publicjson: String,
+    /// Curve, bn256 or bls12_381 [default: the curve recorded in the verification key, or bn256]
+    #[clap(long = "curve")]
+    curve: Option<Curve>,
 }
 
 /// A subcommand for generating a Solidity verifier smart contract
@@ -161,9 +329,15 @@ struct GenerateRecursiveVerifierOpts {
     /// Original individual verification key file
     #[clap(short = "o", long = "old_vk", default_value = "vk.bin")]
     old_vk: String,
+    /// Original individual verification key list text file, to aggregate proofs of several circuits instead of --old_vk
+    #[clap(long = "old_vk_list")]
+    old_vk_list: Option<String>,
     /// Aggregated verification key file
     #[clap(short = "n", long = "new_vk", default_value = "recursive_vk.bin")]
     new_vk: String,
+    /// Depth of the tree of original verification keys, which holds up to 2^depth of them [default: the depth recorded in --new_vk, or 7]
+    #[clap(long = "vk_tree_depth")]
+    vk_tree_depth: Option<usize>,
     /// Num of inputs
     #[clap(short = "i", long = "num_inputs")]
     num_inputs: usize,
@@ -180,9 +354,15 @@ struct GenerateRecursiveVerifierOpts {
 /// A subcommand for exporting verifying keys
 #[derive(Clap)]
 struct ExportVerificationKeyOpts {
-    /// Source file for Plonk universal setup srs in monomial form
+    /// Proof system, plonk or groth16
+    #[clap(long = "backend", default_value = "plonk")]
+    backend: Backend,
+    /// Source file for Plonk universal setup srs in monomial form, required by plonk
     #[clap(short = "m", long = "srs_monomial_form")]
-    srs_monomial_form: String,
+    srs_monomial_form: Option<String>,
+    /// Source file for groth16 parameters
+    #[clap(long = "params", default_value = "groth16_params.bin")]
+    params: String,
     /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
     #[clap(short = "c", long = "circuit")]
     circuit: Option<String>,
@@ -195,6 +375,19 @@ struct ExportVerificationKeyOpts {
     overwrite: bool,
 }
 
+/// A subcommand for exporting proving keys
+#[derive(Clap)]
+struct ExportProvingKeyOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Output proving key file
+    #[clap(short = "k", long = "proving_key", default_value = "proving_key.bin")]
+    proving_key: String,
+    #[clap(long = "overwrite")]
+    overwrite: bool,
+}
+
 /// A subcommand for exporting recursive verifying keys
 #[derive(Clap)]
 struct ExportRecursiveVerificationKeyOpts {
@@ -204,12 +397,18 @@ struct ExportRecursiveVerificationKeyOpts {
     /// Num of inputs
     #[clap(short = "i", long = "num_inputs")]
     num_inputs: usize,
+    /// Depth of the tree of original verification keys, which holds up to 2^depth of them
+    #[clap(long = "vk_tree_depth", default_value = "7")]
+    vk_tree_depth: usize,
     /// Source file for a BIG Plonk universal setup srs in monomial form
     #[clap(short = "m", long = "srs_monomial_form")]
     srs_monomial_form: String,
     /// Output verifying key file
     #[clap(short = "v", long = "vk", default_value = "recursive_vk.bin")]
     vk: String,
+    /// Output file for the recursive proving key, which recursive-prove loads instead of setting up the recursive circuit again
+    #[clap(short = "k", long = "proving_key")]
+    proving_key: Option<String>,
     #[clap(long = "overwrite")]
     overwrite: bool,
 }
@@ -226,6 +425,27 @@ struct RecursiveProveOpts {
     /// Old vk
     #[clap(short = "v", long = "old_vk", default_value = "vk.bin")]
     old_vk: String,
+    /// Old vk list text file, to aggregate proofs of several circuits instead of --old_vk
+    #[clap(long = "old_vk_list")]
+    old_vk_list: Option<String>,
+    /// Comma separated index in --old_vk_list of the vk of every old proof [default: 0 for every proof]
+    #[clap(long = "vk_indexes", use_delimiter = true)]
+    vk_indexes: Vec<usize>,
+    /// Num of proofs to check, as the recursive verification key was exported for [default: the number recorded in --proving_key, or the number of old proofs]
+    #[clap(short = "c", long = "num_proofs_to_check")]
+    num_proofs_to_check: Option<usize>,
+    /// Proof BIN file, valid with the rescue transcript, repeated to fill the old proofs up to --num_proofs_to_check
+    #[clap(long = "padding_proof")]
+    padding_proof: Option<String>,
+    /// Index in --old_vk_list of the vk of --padding_proof
+    #[clap(long = "padding_vk_index", default_value = "0")]
+    padding_vk_index: usize,
+    /// Depth of the tree of original verification keys, which holds up to 2^depth of them [default: the depth recorded in --proving_key, or 7]
+    #[clap(long = "vk_tree_depth")]
+    vk_tree_depth: Option<usize>,
+    /// Recursive proving key file generated by export-recursive-verification-key, skipping the recursive circuit setup
+    #[clap(short = "k", long = "proving_key")]
+    proving_key: Option<String>,
     /// Output file for aggregated proof BIN
     #[clap(short = "n", long = "new_proof", default_value = "recursive_proof.bin")]
     new_proof: String,
@@ -256,14 +476,177 @@ struct CheckAggregationOpts {
     /// Old vk
     #[clap(short = "v", long = "old_vk", default_value = "vk.bin")]
     old_vk: String,
+    /// Old vk list text file, for proofs of several circuits, each checked with the vk at the index recorded in the aggregated proof
+    #[clap(long = "old_vk_list")]
+    old_vk_list: Option<String>,
+    /// Proof BIN file the old proofs were padded with, up to the number of proofs the aggregated proof checks
+    #[clap(long = "padding_proof")]
+    padding_proof: Option<String>,
     /// Aggregated Proof BIN file
     #[clap(short = "n", long = "new_proof", default_value = "recursive_proof.bin")]
     new_proof: String,
+    /// Depth of the tree of original verification keys, which holds up to 2^depth of them [default: the depth recorded in --new_proof, or 7]
+    #[clap(long = "vk_tree_depth")]
+    vk_tree_depth: Option<usize>,
+}
+
+/// A subcommand for serving prove, verify and analyse over HTTP
+#[derive(Clap)]
+struct ServeOpts {
+    /// Source file for Plonk universal setup srs in monomial form
+    #[clap(short = "m", long = "srs_monomial_form")]
+    srs_monomial_form: String,
+    /// Curve of the srs and the served circuits, bn256 or bls12_381
+    #[clap(long = "curve", default_value = "bn256")]
+    curve: Curve,
+    /// Address to listen on
+    #[clap(long = "address", default_value = "127.0.0.1:8080")]
+    address: String,
+    /// Number of jobs running at the same time
+    #[clap(long = "jobs", default_value = "1")]
+    jobs: usize,
+    /// Number of jobs waiting to run, further requests are rejected with 503
+    #[clap(long = "queue", default_value = "16")]
+    queue: usize,
+    /// Directory the files named in requests must be in, relative names are taken from it
+    #[clap(long = "root", default_value = ".")]
+    root: String,
+    /// Number of circuit setups kept in memory, the least recently used one is dropped past it
+    #[clap(long = "max_cached", default_value = "8")]
+    max_cached: usize,
+}
+
+// call a subcommand generic over the engine, with the engine of the given curve
+macro_rules! with_engine {
+    ($curve:expr, $subcommand:ident($($arg:expr),*)) => {
+        match $curve {
+            Curve::Bn256 => $subcommand::<Bn256>($($arg),*),
+            Curve::Bls12_381 => $subcommand::<Bls12>($($arg),*),
+        }
+    };
+}
+
+// process exit codes besides 0 for success, and 1 for invalid command lines which clap reports
+const EXIT_CHECK_FAILED: i32 = 2;
+const EXIT_BAD_INPUT: i32 = 3;
+const EXIT_IO: i32 = 4;
+const EXIT_INTERNAL: i32 = 5;
+
+const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    Success
+    1    Invalid command line
+    2    A proof is invalid, or a check failed
+    3    Malformed input files or invalid arguments
+    4    Failed to read an input or write an output
+    5    Internal error";
+
+// a proof which is invalid or a check which fails, as opposed to an error which keeps them from running
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct CheckFailed(String);
+
+// the exit code of the first cause of an error which tells what kind of error it is
+fn exit_code(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if cause.is::<CheckFailed>() {
+            return EXIT_CHECK_FAILED;
+        }
+        if cause.is::<plonkit::InvalidInput>() {
+            return EXIT_BAD_INPUT;
+        }
+        if let Some(e) = cause.downcast_ref::<reader::Error>() {
+            return match e {
+                reader::Error::Open { .. } => EXIT_IO,
+                reader::Error::Io(e) => io_exit_code(e),
+                _ => EXIT_BAD_INPUT,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            return io_exit_code(e);
+        }
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            return if e.is_io() { EXIT_IO } else { EXIT_BAD_INPUT };
+        }
+        if let Some(e) = cause.downcast_ref::<SynthesisError>() {
+            return match e {
+                SynthesisError::IoError(_) => EXIT_IO,
+                SynthesisError::Unsatisfiable => EXIT_BAD_INPUT,
+                _ => EXIT_INTERNAL,
+            };
+        }
+    }
+    // anything else, such as an error of bellman or of the recursive circuit, is internal
+    EXIT_INTERNAL
+}
+
+// a file ending early or holding garbage is a bad input, not a failure to read it
+fn io_exit_code(err: &std::io::Error) -> i32 {
+    match err.kind() {
+        std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::InvalidData => EXIT_BAD_INPUT,
+        _ => EXIT_IO,
+    }
+}
+
+// what a subcommand found and wrote, printed to stdout with --output-format json
+#[derive(Default, Serialize)]
+struct Report {
+    // whether the proofs are valid, for the subcommands verifying them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valid: Option<bool>,
+    // the files written, by what they hold
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    artifacts: BTreeMap<&'static str, String>,
+    // seconds taken by the phases of the subcommand
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    timings: BTreeMap<&'static str, f64>,
+    // anything else of interest, such as gate counts
+    #[serde(flatten)]
+    details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Report {
+    fn artifact(&mut self, what: &'static str, path: &str) {
+        self.artifacts.insert(what, path.to_owned());
+    }
+
+    fn detail<T: Serialize>(&mut self, key: &str, value: T) {
+        let value = serde_json::to_value(value).unwrap_or(serde_json::Value::Null);
+        self.details.insert(key.to_owned(), value);
+    }
+
+    // run a phase of the subcommand, and record how long it took
+    fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.timings.insert(phase, started.elapsed().as_secs_f64());
+        result
+    }
+}
+
+// the result of a command as printed with --output-format json
+#[derive(Serialize)]
+struct CommandOutput<'a> {
+    command: &'static str,
+    // success, failed when a proof is invalid or a check fails, or error
+    status: &'static str,
+    exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    elapsed: f64,
+    #[serde(flatten)]
+    report: &'a Report,
+}
+
+// the message of a panic, which is a string unless panicking with a custom payload
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(s), _) => s.to_string(),
+        (_, Some(s)) => s.clone(),
+        _ => "panicked".to_owned(),
+    }
 }
 
 fn main() {
-    // Always print backtrace on panic.
-    ::std::env::set_var("RUST_BACKTRACE", "1");
     match ::std::env::var("RUST_LOG") {
         Ok(value) => {
             if value.is_empty() {
@@ -275,77 +658,200 @@ fn main() {
     env_logger::init();
 
     let opts: Opts = Opts::parse();
-    match opts.command {
-        SubCommand::Analyse(o) => {
-            analyse(o);
-        }
-        SubCommand::Setup(o) => {
-            setup(o);
-        }
-        SubCommand::DumpLagrange(o) => {
-            dump_lagrange(o);
-        }
-        SubCommand::Prove(o) => {
-            prove(o);
-        }
-        SubCommand::Verify(o) => {
-            verify(o);
-        }
-        SubCommand::GenerateVerifier(o) => {
-            generate_verifier(o);
+    let command = opts.command.name();
+    let mut report = Report::default();
+    let started = Instant::now();
+    // panics are reported by the default hook, with a backtrace if RUST_BACKTRACE is set
+    let (code, error) = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run(opts.command, &mut report))) {
+        Ok(Ok(())) => (0, None),
+        Ok(Err(err)) => {
+            log::error!("{:#}", err);
+            (exit_code(&err), Some(format!("{:#}", err)))
         }
-        SubCommand::GenerateRecursiveVerifier(o) => {
-            generate_recursive_verifier(o);
-        }
-        SubCommand::ExportVerificationKey(o) => {
-            export_vk(o);
-        }
-        SubCommand::ExportRecursiveVerificationKey(o) => {
-            export_recursive_vk(o);
-        }
-        SubCommand::RecursiveProve(o) => {
-            recursive_prove(o);
-        }
-        SubCommand::RecursiveVerify(o) => {
-            recursive_verify(o);
+        Err(payload) => (EXIT_INTERNAL, Some(panic_message(&*payload))),
+    };
+    if opts.output_format == OutputFormat::Json {
+        let output = CommandOutput {
+            command,
+            status: match code {
+                0 => "success",
+                EXIT_CHECK_FAILED => "failed",
+                _ => "error",
+            },
+            exit_code: code,
+            error,
+            elapsed: started.elapsed().as_secs_f64(),
+            report: &report,
+        };
+        println!("{}", serde_json::to_string(&output).unwrap_or_else(|_| "{}".to_owned()));
+    }
+    std::process::exit(code);
+}
+
+fn run(command: SubCommand, report: &mut Report) -> Result<(), anyhow::Error> {
+    match command {
+        SubCommand::Analyse(o) => circuit_curve(&o.circuit).and_then(|curve| with_engine!(curve, analyse(o, report))),
+        SubCommand::Setup(o) => setup(o, report),
+        SubCommand::ImportPtau(o) => import_ptau(o, report),
+        SubCommand::DumpLagrange(o) => circuit_curve(&o.circuit).and_then(|curve| with_engine!(curve, dump_lagrange(o, report))),
+        SubCommand::CheckWitness(o) => circuit_curve(&o.circuit).and_then(|curve| with_engine!(curve, check_witness(o, report))),
+        SubCommand::CheckUnderconstrained(o) => {
+            circuit_curve(&o.circuit).and_then(|curve| with_engine!(curve, check_underconstrained(o, report)))
         }
-        SubCommand::CheckAggregation(o) => {
-            check_aggregation(o);
+        SubCommand::Prove(o) => circuit_curve(&o.circuit).and_then(|curve| with_engine!(curve, prove(o, report))),
+        SubCommand::Verify(o) => verification_key_curve(&o).and_then(|curve| with_engine!(curve, verify(o, report))),
+        SubCommand::GenerateVerifier(o) => generate_verifier(o, report),
+        SubCommand::GenerateRecursiveVerifier(o) => generate_recursive_verifier(o, report),
+        SubCommand::ExportVerificationKey(o) => match o.backend {
+            Backend::Plonk => circuit_curve(&o.circuit),
+            Backend::Groth16 => reader::load_groth16_parameters_curve(&o.params).map_err(anyhow::Error::from),
         }
+        .and_then(|curve| with_engine!(curve, export_vk(o, report))),
+        SubCommand::ExportProvingKey(o) => circuit_curve(&o.circuit).and_then(|curve| with_engine!(curve, export_pk(o, report))),
+        SubCommand::ExportRecursiveVerificationKey(o) => export_recursive_vk(o, report),
+        SubCommand::RecursiveProve(o) => recursive_prove(o, report),
+        SubCommand::RecursiveVerify(o) => recursive_verify(o, report),
+        SubCommand::CheckAggregation(o) => check_aggregation(o, report),
+        SubCommand::Serve(o) => with_engine!(o.curve, serve(o)),
     }
 }
 
 // analyse the contraints statistics of a circuit, and print it out
-fn analyse(opts: AnalyseOpts) {
+fn analyse<E: CircomEngine>(opts: AnalyseOpts, report: &mut Report) -> Result<(), anyhow::Error> {
     let circuit_file = resolve_circuit_file(opts.circuit);
     log::info!("Loading circuit from {}...", circuit_file);
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(&circuit_file),
+        r1cs: reader::load_r1cs::<E>(&circuit_file)?,
         witness: None,
         wire_mapping: None,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: resolve_symbols(opts.sym, &circuit_file)?,
     };
-    let mut stats = plonk::analyse(circuit).expect("analyse failed");
-    let writer = File::create(&opts.output).unwrap();
-    serde_json::to_writer_pretty(writer, &stats).expect("write failed");
+    anyhow::ensure!(
+        opts.folded.is_none() || circuit.symbols.is_some(),
+        invalid_input!("--folded needs the circuit symbols, see --sym")
+    );
+    let mut stats = report.time("analyse", || plonk::analyse(circuit))?;
+    write_atomically(&opts.output, |w| Ok(serde_json::to_writer_pretty(w, &stats)?))?;
+    report.artifact("output", &opts.output);
+    if let Some(folded) = &opts.folded {
+        write_atomically(folded, |w| w.write_all(plonk::folded_stacks(&stats.constraint_stats).as_bytes()))?;
+        report.artifact("folded", folded);
+        log::info!("folded stacks output to {}", folded);
+    }
     stats.constraint_stats.clear();
+    stats.component_stats.truncate(10);
+    if !stats.custom_gate_stats.is_empty() {
+        log::warn!("circuit uses custom gates, which are not supported by the plonk prover");
+    }
     log::info!(
         "analyse result: {}",
         serde_json::to_string_pretty(&stats).unwrap_or_else(|_| "<failed>".to_owned())
     );
+    report.detail("analyse", &stats);
     log::info!("output to {}", opts.output);
+    Ok(())
 }
 
 // generate a monomial_form SRS, and save it to a file
-fn setup(opts: SetupOpts) {
-    let srs = plonk::gen_key_monomial_form(opts.power).unwrap();
-    if !opts.overwrite {
-        let path = Path::new(&opts.srs_monomial_form);
-        assert!(!path.exists(), "duplicate srs_monomial_form file: {}", path.display());
-    }
-    let writer = File::create(&opts.srs_monomial_form).unwrap();
-    srs.write(writer).unwrap();
+fn setup(opts: SetupOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    if opts.backend == Backend::Groth16 {
+        let curve = circuit_curve(&opts.circuit)?;
+        return with_engine!(curve, setup_groth16(opts, report));
+    }
+    let power = opts
+        .power
+        .ok_or_else(|| invalid_input!("--power is required by the plonk backend"))?;
+    let srs_monomial_form = opts
+        .srs_monomial_form
+        .ok_or_else(|| invalid_input!("--srs_monomial_form is required by the plonk backend"))?;
+    check_outputs(opts.overwrite, &[&srs_monomial_form])?;
+    match opts.curve {
+        Curve::Bn256 => {
+            let srs = report.time("setup", || plonk::gen_key_monomial_form::<Bn256>(power))?;
+            write_atomically(&srs_monomial_form, |w| srs.write(w))?
+        }
+        Curve::Bls12_381 => {
+            let srs = report.time("setup", || plonk::gen_key_monomial_form::<Bls12>(power))?;
+            write_atomically(&srs_monomial_form, |w| srs.write(w))?
+        }
+    }
+    report.artifact("srs_monomial_form", &srs_monomial_form);
+    report.detail("curve", opts.curve.to_string());
+    log::info!("{} srs_monomial_form saved to {}", opts.curve, srs_monomial_form);
+    Ok(())
+}
+
+// generate groth16 parameters for a circuit, and save them to a file
+fn setup_groth16<E: CircomEngine>(opts: SetupOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.params])?;
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    log::info!("Loading circuit from {}...", circuit_file);
+    let (r1cs, wire_mapping) = reader::load_r1cs_with_wire_mapping::<E>(&circuit_file)?;
+    r1cs.ensure_no_custom_gates()?;
+    let digest = r1cs.digest();
+    let circuit = CircomCircuit {
+        r1cs,
+        witness: None,
+        wire_mapping,
+        aux_offset: groth16::AUX_OFFSET,
+        symbols: None,
+    };
+    log::info!("Generating groth16 parameters...");
+    let params = report.time("setup", || groth16::ProvingParameters::generate(circuit, digest))?;
+    write_atomically(&opts.params, |w| params.write(w))?;
+    report.artifact("params", &opts.params);
+    report.detail("curve", E::CURVE.to_string());
+    log::info!("groth16 parameters saved to {}", opts.params);
+    Ok(())
+}
+
+// convert a Powers of Tau file into a monomial_form SRS, and save it to a file
+fn import_ptau(opts: ImportPtauOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.srs_monomial_form])?;
+    log::info!("Loading powers of tau from {}...", opts.ptau);
+    let srs = reader::load_key_monomial_form_from_ptau(&opts.ptau, opts.power)?;
+    write_atomically(&opts.srs_monomial_form, |w| srs.write(w))?;
+    report.artifact("srs_monomial_form", &opts.srs_monomial_form);
     log::info!("srs_monomial_form saved to {}", opts.srs_monomial_form);
+    Ok(())
+}
+
+// fail before doing any work if an output file exists, unless --overwrite is given
+fn check_outputs(overwrite: bool, outputs: &[&str]) -> Result<(), anyhow::Error> {
+    if !overwrite {
+        for output in outputs {
+            anyhow::ensure!(
+                !Path::new(output).exists(),
+                invalid_input!("duplicate output file: {}, pass --overwrite to replace it", output)
+            );
+        }
+    }
+    Ok(())
+}
+
+// the curve a circuit is compiled for, by the prime in its header
+fn circuit_curve(circuit: &Option<String>) -> Result<Curve, anyhow::Error> {
+    Ok(reader::load_curve(&resolve_circuit_file(circuit.clone()))?)
+}
+
+// the curve to verify with, from the verification key meta, which groth16 keys and legacy plonk keys don't have
+fn verification_key_curve(opts: &VerifyOpts) -> Result<Curve, anyhow::Error> {
+    let recorded = match opts.backend {
+        Backend::Plonk => reader::load_artifact_meta(&opts.vk)?.map(|meta| meta.curve),
+        Backend::Groth16 => None,
+    };
+    match (recorded, opts.curve) {
+        (Some(recorded), Some(curve)) if recorded != curve => {
+            anyhow::bail!(invalid_input!(
+                "verification key {} is for the {} curve, not {}",
+                opts.vk,
+                recorded,
+                curve
+            ))
+        }
+        (recorded, curve) => Ok(recorded.or(curve).unwrap_or(Curve::Bn256)),
+    }
 }
 
 // circuit filename default resolver
@@ -362,257 +868,718 @@ fn resolve_circuit_file(filename: Option<String>) -> String {
     }
 }
 
+// load the circuit symbols from the given file, or from the .sym file next to the circuit if there is one
+fn resolve_symbols(filename: Option<String>, circuit_file: &str) -> Result<Option<CircuitSymbols>, anyhow::Error> {
+    let filename = match filename {
+        Some(s) => s,
+        None => {
+            let path = Path::new(circuit_file).with_extension("sym");
+            if !path.exists() {
+                return Ok(None);
+            }
+            path.to_string_lossy().into_owned()
+        }
+    };
+    log::info!("Loading symbols from {}...", filename);
+    Ok(Some(reader::load_symbols(&filename)?))
+}
+
 // generate a lagrange_form SRS from a monomial_form SRS, and save it to a file
-fn dump_lagrange(opts: DumpLagrangeOpts) {
+fn dump_lagrange<E: PlonkEngine>(opts: DumpLagrangeOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.srs_lagrange_form])?;
     let circuit_file = resolve_circuit_file(opts.circuit);
     log::info!("Loading circuit from {}...", circuit_file);
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(&circuit_file),
+        r1cs: reader::load_r1cs::<E>(&circuit_file)?,
         witness: None,
         wire_mapping: None,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
     };
+    circuit.r1cs.ensure_no_custom_gates()?;
 
-    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit, reader::load_key_monomial_form(&opts.srs_monomial_form), None)
-        .expect("prepare err");
+    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit, reader::load_key_monomial_form(&opts.srs_monomial_form)?, None)?;
 
-    let key_lagrange_form = setup.get_srs_lagrange_form_from_monomial_form();
-    if !opts.overwrite {
-        let path = Path::new(&opts.srs_lagrange_form);
-        assert!(!path.exists(), "duplicate srs_lagrange_form file: {}", path.display());
-    }
-    let writer = File::create(&opts.srs_lagrange_form).unwrap();
-    key_lagrange_form.write(writer).unwrap();
+    let key_lagrange_form = report.time("dump", || setup.get_srs_lagrange_form_from_monomial_form());
+    write_atomically(&opts.srs_lagrange_form, |w| key_lagrange_form.write(w))?;
+    report.artifact("srs_lagrange_form", &opts.srs_lagrange_form);
     log::info!("srs_lagrange_form saved to {}", opts.srs_lagrange_form);
+    Ok(())
 }
 
-// generate a plonk proof for a circuit, with witness loaded, and save the proof to a file
-fn prove(opts: ProveOpts) {
+// evaluate the circuit constraints over a witness, and list the unsatisfied ones
+fn check_witness<E: CircomEngine>(opts: CheckWitnessOpts, report: &mut Report) -> Result<(), anyhow::Error> {
     let circuit_file = resolve_circuit_file(opts.circuit);
     log::info!("Loading circuit from {}...", circuit_file);
+    let (r1cs, wire_mapping) = reader::load_r1cs_with_wire_mapping(&circuit_file)?;
+    let witness = reader::load_witness_from_file::<E>(&opts.witness)?;
+    let wire_mapping = circom_circuit::wire_mapping_for_witness(wire_mapping, witness.len(), r1cs.num_variables)?;
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(&circuit_file),
-        witness: Some(reader::load_witness_from_file::<Bn256>(&opts.witness)),
-        wire_mapping: None,
+        r1cs,
+        witness: Some(witness),
+        wire_mapping,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: resolve_symbols(opts.sym, &circuit_file)?,
     };
 
-    let setup = plonk::SetupForProver::prepare_setup_for_prover(
-        circuit.clone(),
-        reader::load_key_monomial_form(&opts.srs_monomial_form),
-        reader::maybe_load_key_lagrange_form(opts.srs_lagrange_form),
-    )
-    .expect("prepare err");
+    let unsatisfied = circuit.unsatisfied_constraints()?;
+    report.detail("num_constraints", circuit.r1cs.constraints.len());
+    report.detail("unsatisfied_constraints", unsatisfied.iter().map(|c| c.index).collect::<Vec<_>>());
+    for c in &unsatisfied {
+        log::error!(
+            "constraint {} is not satisfied: A*w = {}, B*w = {}, C*w = {}",
+            c.index,
+            repr_to_big(c.a.into_repr()),
+            repr_to_big(c.b.into_repr()),
+            repr_to_big(c.c.into_repr())
+        );
+        for (wire, value) in &c.wires {
+            log::error!("    {} = {}", circuit.wire_name(*wire), repr_to_big(value.into_repr()));
+        }
+    }
+    if !unsatisfied.is_empty() {
+        return Err(CheckFailed(format!(
+            "{} of {} constraints are not satisfied",
+            unsatisfied.len(),
+            circuit.r1cs.constraints.len()
+        ))
+        .into());
+    }
+    log::info!("All {} constraints are satisfied.", circuit.r1cs.constraints.len());
+    Ok(())
+}
 
+// look for signals which the circuit constraints don't pin down, and save the report to a file
+fn check_underconstrained<E: CircomEngine>(opts: CheckUnderconstrainedOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    log::info!("Loading circuit from {}...", circuit_file);
+    let r1cs = reader::load_r1cs::<E>(&circuit_file)?;
+    let symbols = resolve_symbols(opts.sym, &circuit_file)?;
+    let findings = underconstrained::analyse(&r1cs, symbols.as_ref());
+    write_atomically(&opts.output, |w| Ok(serde_json::to_writer_pretty(w, &findings)?))?;
+    report.artifact("output", &opts.output);
+    report.detail("num_findings", findings.num_findings());
+    let kinds = [
+        ("private signals in no constraint", &findings.unused_signals),
+        ("linearly constrained signals the inputs leave free", &findings.linear_only_signals),
+        ("unconstrained public inputs", &findings.unconstrained_public_inputs),
+        ("outputs independent of the inputs", &findings.outputs_independent_of_inputs),
+    ];
+    for (what, signals) in &kinds {
+        if !signals.is_empty() {
+            let names: Vec<String> = signals
+                .iter()
+                .map(|s| s.name.clone().unwrap_or_else(|| format!("wire {}", s.wire)))
+                .collect();
+            log::warn!("{} {}: {}", signals.len(), what, names.join(", "));
+        }
+    }
+    if findings.num_findings() == 0 {
+        log::info!("No underconstrained signals found.");
+    }
+    log::info!("output to {}", opts.output);
+    Ok(())
+}
+
+// how public.json lists the public inputs of a plonk proof
+trait PublicInputsJson: PlonkEngine {
+    fn public_inputs_json(proof: &Proof<Self, PlonkCsWidth4WithNextStepParams>) -> String {
+        let inputs: Vec<String> = proof.input_values.iter().map(|x| repr_to_big(x.into_repr())).collect();
+        serde_json::to_string_pretty(&inputs).unwrap()
+    }
+}
+
+// as the solidity verifier takes them
+impl PublicInputsJson for Bn256 {
+    fn public_inputs_json(proof: &Proof<Self, PlonkCsWidth4WithNextStepParams>) -> String {
+        let (inputs, _) = bellman_vk_codegen::serialize_proof(proof);
+        serde_json::to_string_pretty(&inputs).unwrap()
+    }
+}
+
+impl PublicInputsJson for Bls12 {}
+
+// generate a plonk or groth16 proof for a circuit, with witness loaded, and save the proof to a file
+fn prove<E: PublicInputsJson>(opts: ProveOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    let proof_meta = plonk::artifact_meta_file(&opts.proof);
+    let mut outputs = vec![opts.proof.as_str(), &opts.proofjson, &opts.publicjson];
+    outputs.extend(opts.named_publicjson.as_deref());
+    if opts.backend == Backend::Plonk {
+        outputs.push(&proof_meta);
+    }
+    check_outputs(opts.overwrite, &outputs)?;
+    let circuit_file = resolve_circuit_file(opts.circuit.clone());
+    log::info!("Loading circuit from {}...", circuit_file);
+    let (r1cs, wire_mapping) = reader::load_r1cs_with_wire_mapping(&circuit_file)?;
+    let witness = match (&opts.input, &opts.wasm) {
+        (Some(input), Some(wasm)) => {
+            log::info!("Calculating witness from {} with {}...", input, wasm);
+            report.time("witness", || witness::calculate_witness_from_files::<E>(wasm, input))?
+        }
+        (None, None) => reader::load_witness_from_file::<E>(&opts.witness)?,
+        _ => anyhow::bail!(invalid_input!("--input and --wasm must be used together")),
+    };
+    let wire_mapping = circom_circuit::wire_mapping_for_witness(wire_mapping, witness.len(), r1cs.num_variables)?;
+    let symbols = match &opts.named_publicjson {
+        Some(_) => {
+            let symbols = resolve_symbols(opts.sym.clone(), &circuit_file)?;
+            anyhow::ensure!(
+                symbols.is_some(),
+                invalid_input!("--named_publicjson needs the circuit symbols, see --sym")
+            );
+            symbols
+        }
+        None => None,
+    };
+    let circuit = CircomCircuit {
+        r1cs,
+        witness: Some(witness),
+        wire_mapping,
+        aux_offset: match opts.backend {
+            Backend::Plonk => plonk::AUX_OFFSET,
+            Backend::Groth16 => groth16::AUX_OFFSET,
+        },
+        symbols,
+    };
+    circuit.r1cs.ensure_no_custom_gates()?;
+    if opts.backend == Backend::Groth16 {
+        return prove_groth16(&opts, circuit, &circuit_file, report);
+    }
+    let named_public_inputs = circuit.get_named_public_inputs_json();
+
+    let srs_monomial_form = opts
+        .srs_monomial_form
+        .as_ref()
+        .ok_or_else(|| invalid_input!("--srs_monomial_form is required by the plonk backend"))?;
+    let key_monomial_form = reader::load_key_monomial_form(srs_monomial_form)?;
+    let key_lagrange_form = reader::maybe_load_key_lagrange_form(opts.srs_lagrange_form)?;
+    let proving_key = match &opts.proving_key {
+        Some(proving_key_file) => {
+            log::info!("Loading proving key from {}...", proving_key_file);
+            let proving_key = reader::load_proving_key(proving_key_file)?;
+            anyhow::ensure!(
+                proving_key.circuit_digest == circuit.r1cs.digest(),
+                invalid_input!("proving key {} is not generated from circuit {}", proving_key_file, circuit_file)
+            );
+            Some(proving_key)
+        }
+        None => None,
+    };
+    let setup = report.time("setup", || match proving_key {
+        Some(proving_key) => {
+            plonk::SetupForProver::prepare_setup_for_prover_from_proving_key(proving_key, key_monomial_form, key_lagrange_form)
+        }
+        None => plonk::SetupForProver::prepare_setup_for_prover(circuit.clone(), key_monomial_form, key_lagrange_form),
+    })?;
+    report.detail("num_gates", setup.num_gates());
+    report.detail("srs_power", setup.srs_power());
+    report.detail("transcript", opts.transcript.to_string());
+
+    let meta = plonk::ArtifactMeta {
+        curve: E::CURVE,
+        transcript: Some(opts.transcript),
+        circuit_digest: circuit.r1cs.digest(),
+        srs_power: setup.srs_power(),
+    };
     log::info!("Proving...");
-    let proof = setup.prove(circuit, &opts.transcript).unwrap();
+    let proof = report.time("prove", || setup.prove(circuit, opts.transcript))?;
 
-    println!("proof is {:?}", proof.clone());
-    let proof_clone = proof.clone();
+    log::debug!("proof is {:?}", proof);
 
     let proof_str = ProofStr {
-        num_inputs: proof_clone.num_inputs,
-        n: proof_clone.n,
-        input_values: proof_clone.input_values.into_iter().map(|x| to_hex(&x)).collect(),
-        wire_commitments: proof_clone.wire_commitments.into_iter().map(|x| hex::encode(G1Uncompressed::from_affine(x))).collect(),
-        grand_product_commitment: hex::encode(G1Uncompressed::from_affine(proof_clone.grand_product_commitment)),
-        quotient_poly_commitments: proof_clone.quotient_poly_commitments.into_iter().map(|x| hex::encode(G1Uncompressed::from_affine(x))).collect(),
-        wire_values_at_z: proof_clone.wire_values_at_z.into_iter().map(|x| to_hex(&x)).collect(),
-        wire_values_at_z_omega: proof_clone.wire_values_at_z_omega.into_iter().map(|x| to_hex(&x)).collect(),
-        grand_product_at_z_omega: to_hex(&proof_clone.grand_product_at_z_omega),
-        quotient_polynomial_at_z: to_hex(&proof_clone.quotient_polynomial_at_z),
-        linearization_polynomial_at_z: to_hex(&proof_clone.linearization_polynomial_at_z),
-        permutation_polynomials_at_z: proof_clone.permutation_polynomials_at_z.into_iter().map(|x| to_hex(&x)).collect(),
-        opening_at_z_proof: hex::encode(G1Uncompressed::from_affine(proof_clone.opening_at_z_proof)),
-        opening_at_z_omega_proof: hex::encode(G1Uncompressed::from_affine(proof_clone.opening_at_z_omega_proof))
+        meta: Some(ArtifactMetaStr::from(&meta)),
+        ..ProofStr::from(&proof)
     };
 
     let json_proof = serde_json::to_string(&proof_str).unwrap();
-    if !opts.overwrite {
-        let path = Path::new(&opts.proofjson);
-        assert!(!path.exists(), "duplicate proof json file: {}", path.display());
-        let path = Path::new(&opts.publicjson);
-        assert!(!path.exists(), "duplicate input json file: {}", path.display());
-    }
-    // write proof.json
-    if let Err(err) = std::fs::write(&opts.proofjson, json_proof) {
-        eprintln!("Error writing file: {}", err);
-    }
+    write_atomically(&opts.proofjson, |w| w.write_all(json_proof.as_bytes()))?;
+    report.artifact("proofjson", &opts.proofjson);
 
-    if !opts.overwrite {
-        let path = Path::new(&opts.proof);
-        assert!(!path.exists(), "duplicate proof file: {}", path.display());
-    }
-    let writer = File::create(&opts.proof).unwrap();
-    proof.write(writer).unwrap();
+    write_atomically(&opts.proof, |w| proof.write(w))?;
+    report.artifact("proof", &opts.proof);
     log::info!("Proof bin saved to {}", opts.proof);
 
-    let (inputs, serialized_proof) = bellman_vk_codegen::serialize_proof(&proof);
-    let ser_inputs_str = serde_json::to_string_pretty(&inputs).unwrap();
-    
-    std::fs::write(&opts.publicjson, ser_inputs_str.as_bytes()).expect("save publicjson err");
+    write_atomically(&proof_meta, |w| meta.write(w))?;
+    report.artifact("proof_meta", &proof_meta);
+
+    let ser_inputs_str = E::public_inputs_json(&proof);
+    write_atomically(&opts.publicjson, |w| w.write_all(ser_inputs_str.as_bytes()))?;
+    report.artifact("publicjson", &opts.publicjson);
+    log::info!("Public input json saved to {}", opts.publicjson);
+
+    if let Some(named_publicjson) = &opts.named_publicjson {
+        write_atomically(named_publicjson, |w| w.write_all(named_public_inputs.as_bytes()))?;
+        report.artifact("named_publicjson", named_publicjson);
+        log::info!("Named public input json saved to {}", named_publicjson);
+    }
+    Ok(())
+}
+
+// generate a groth16 proof for a circuit, with witness loaded, and save the proof and its public inputs to files
+fn prove_groth16<E: CircomEngine>(
+    opts: &ProveOpts,
+    circuit: CircomCircuit<E>,
+    circuit_file: &str,
+    report: &mut Report,
+) -> Result<(), anyhow::Error> {
+    log::info!("Loading groth16 parameters from {}...", opts.params);
+    let params = reader::load_groth16_parameters::<E>(&opts.params)?;
+    anyhow::ensure!(
+        params.circuit_digest == circuit.r1cs.digest(),
+        invalid_input!("groth16 parameters {} are not generated from circuit {}", opts.params, circuit_file)
+    );
+    let public_inputs: Vec<String> = circuit
+        .get_public_inputs()
+        .unwrap()
+        .iter()
+        .map(|x| repr_to_big(x.into_repr()))
+        .collect();
+    let named_public_inputs = circuit.get_named_public_inputs_json();
+
+    log::info!("Proving...");
+    let proof = report.time("prove", || groth16::prove(circuit, &params))?;
+
+    write_atomically(&opts.proof, |w| proof.write(w))?;
+    report.artifact("proof", &opts.proof);
+    log::info!("Proof bin saved to {}", opts.proof);
+    write_atomically(&opts.proofjson, |w| Ok(serde_json::to_writer(w, &Groth16ProofStr::from(&proof))?))?;
+    report.artifact("proofjson", &opts.proofjson);
+    log::info!("Proof json saved to {}", opts.proofjson);
+    write_atomically(&opts.publicjson, |w| Ok(serde_json::to_writer_pretty(w, &public_inputs)?))?;
+    report.artifact("publicjson", &opts.publicjson);
     log::info!("Public input json saved to {}", opts.publicjson);
+    if let Some(named_publicjson) = &opts.named_publicjson {
+        write_atomically(named_publicjson, |w| w.write_all(named_public_inputs.as_bytes()))?;
+        report.artifact("named_publicjson", named_publicjson);
+        log::info!("Named public input json saved to {}", named_publicjson);
+    }
+    Ok(())
 }
 
 // verify a plonk proof by using a verification key
-fn verify(opts: VerifyOpts) {
-    let vk = reader::load_verification_key::<Bn256>(&opts.vk);
+fn verify<E: PlonkEngine>(opts: VerifyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    if opts.backend == Backend::Groth16 {
+        return verify_groth16::<E>(opts, report);
+    }
+    let (vk, vk_meta) = if opts.vk.ends_with(".json") {
+        reader::load_verification_key_json_with_meta::<E>(&opts.vk)?
+    } else {
+        reader::load_verification_key_with_meta::<E>(&opts.vk)?
+    };
 
-    let proof = reader::load_proof::<Bn256>(&opts.proof);
-    let correct = plonk::verify(&vk, &proof, &opts.transcript).expect("fail to verify proof");
-    if correct {
-        log::info!("Proof is valid.");
+    if let Some(proof_list) = &opts.proof_list {
+        return verify_batch(vk, vk_meta, proof_list, opts.transcript, report);
+    }
+
+    let (proof, proof_meta) = if opts.proof.ends_with(".json") {
+        reader::load_proof_json_with_meta(&opts.proof)?
     } else {
-        log::info!("Proof is invalid!");
-        std::process::exit(400);
+        reader::load_proof_with_meta(&opts.proof)?
+    };
+    let transcript = plonk::check_artifacts(opts.transcript, vk_meta.as_ref(), proof_meta.as_ref())?;
+    log::info!("Verifying with the {} transcript...", transcript);
+    report.detail("transcript", transcript.to_string());
+    let correct = report.time("verify", || plonk::verify(&vk, &proof, transcript))?;
+    report.valid = Some(correct);
+    if !correct {
+        return Err(CheckFailed("Proof is invalid!".to_owned()).into());
     }
+    log::info!("Proof is valid.");
+    Ok(())
+}
+
+// verify a groth16 proof against its public inputs by using a verification key
+fn verify_groth16<E: CircomEngine>(opts: VerifyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    anyhow::ensure!(
+        opts.proof_list.is_none(),
+        invalid_input!("--proof_list is not supported by the groth16 backend")
+    );
+    let vk = reader::load_groth16_verification_key::<E>(&opts.vk)?;
+    let proof = reader::load_groth16_proof(&opts.proof)?;
+    let public_inputs = reader::load_public_inputs_json::<E>(&opts.publicjson)?;
+    anyhow::ensure!(
+        public_inputs.len() + 1 == vk.ic.len(),
+        invalid_input!(
+            "{} has {} public inputs, but the verification key expects {}",
+            opts.publicjson,
+            public_inputs.len(),
+            vk.ic.len() - 1
+        )
+    );
+    log::info!("Verifying...");
+    let correct = report.time("verify", || groth16::verify(&vk, &proof, &public_inputs))?;
+    report.valid = Some(correct);
+    if !correct {
+        return Err(CheckFailed("Proof is invalid!".to_owned()).into());
+    }
+    log::info!("Proof is valid.");
+    Ok(())
+}
+
+// verify a list of plonk proofs of the same circuit at once
+fn verify_batch<E: PlonkEngine>(
+    vk: VerificationKey<E, PlonkCsWidth4WithNextStepParams>,
+    vk_meta: Option<plonk::ArtifactMeta>,
+    proof_list: &str,
+    transcript: Option<plonk::Transcript>,
+    report: &mut Report,
+) -> Result<(), anyhow::Error> {
+    let (proofs, metas): (Vec<_>, Vec<_>) = reader::load_proofs_with_meta_from_list::<E>(proof_list)?.into_iter().unzip();
+    let transcripts = metas
+        .iter()
+        .map(|meta| plonk::check_artifacts(transcript, vk_meta.as_ref(), meta.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    anyhow::ensure!(
+        transcripts.iter().all(|t| *t == transcripts[0]),
+        invalid_input!("proofs in {} use different transcripts", proof_list)
+    );
+    log::info!("Verifying {} proofs with the {} transcript...", proofs.len(), transcripts[0]);
+    report.detail("transcript", transcripts[0].to_string());
+    report.detail("num_proofs", proofs.len());
+    let invalid = report.time("verify", || plonk::verify_batch(&vk, &proofs, transcripts[0]))?;
+    report.valid = Some(invalid.is_empty());
+    report.detail("invalid_proofs", &invalid);
+    if !invalid.is_empty() {
+        return Err(CheckFailed(format!("Proofs {:?} in {} are invalid!", invalid, proof_list)).into());
+    }
+    log::info!("All {} proofs are valid.", proofs.len());
+    Ok(())
 }
 
 // generate a solidity plonk verifier by feeding a verification key, and save it to a file
-fn generate_verifier(opts: GenerateVerifierOpts) {
-    let vk = reader::load_verification_key::<Bn256>(&opts.vk);
-    if !opts.overwrite {
-        let path = Path::new(&opts.sol);
-        assert!(!path.exists(), "duplicate solidity file: {}", path.display());
-    }
-    match opts.tpl {
-        Some(tpl) => {
-            bellman_vk_codegen::render_verification_key(&vk, &tpl, &opts.sol);
-        }
-        None => {
-            bellman_vk_codegen::render_verification_key_from_default_template(&vk, &opts.sol);
+fn generate_verifier(opts: GenerateVerifierOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.sol])?;
+    let vk = reader::load_verification_key::<Bn256>(&opts.vk)?;
+    write_file_atomically(&opts.sol, |tmp| {
+        let tmp = tmp.to_string_lossy();
+        match &opts.tpl {
+            Some(tpl) => {
+                bellman_vk_codegen::render_verification_key(&vk, tpl, &tmp);
+            }
+            None => {
+                bellman_vk_codegen::render_verification_key_from_default_template(&vk, &tmp);
+            }
         }
-    }
+        Ok(())
+    })?;
+    report.artifact("sol", &opts.sol);
     log::info!("Contract saved to {}", opts.sol);
+    Ok(())
 }
 
 // generate a solidity plonk verifier for proof recursion
-fn generate_recursive_verifier(opts: GenerateRecursiveVerifierOpts) {
-    let old_vk = reader::load_verification_key::<Bn256>(&opts.old_vk);
-    let recursive_vk = reader::load_recursive_verification_key(&opts.new_vk);
+fn generate_recursive_verifier(opts: GenerateRecursiveVerifierOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.sol])?;
+    let old_vks = load_old_vks(&opts.old_vk, &opts.old_vk_list)?;
+    let (recursive_vk, meta) = reader::load_recursive_verification_key_with_meta(&opts.new_vk)?;
+    let vk_tree_depth = resolve_vk_tree_depth(opts.vk_tree_depth, meta.as_ref().map(|meta| (meta, opts.new_vk.as_str())))?;
     let config = recurisive_vk_codegen::Config {
-        vk_tree_root: recursive::get_vk_tree_root_hash(old_vk).unwrap(),
-        //vk_max_index: 0, //because we has aggregated only 1 vk
+        vk_tree_root: recursive::get_vk_tree_root_hash(&old_vks, vk_tree_depth)?,
         individual_input_num: opts.num_inputs,
         recursive_vk,
     };
-    if !opts.overwrite {
-        let path = Path::new(&opts.sol);
-        assert!(!path.exists(), "duplicate solidity file: {}", path.display());
-    }
-    match opts.tpl {
-        Some(tpl) => {
-            recurisive_vk_codegen::create_verifier_contract_from_template(config, &tpl, &opts.sol);
-        }
-        None => {
-            recurisive_vk_codegen::create_verifier_contract_from_default_template(config, &opts.sol);
+    write_file_atomically(&opts.sol, |tmp| {
+        let tmp = tmp.to_string_lossy();
+        match &opts.tpl {
+            Some(tpl) => {
+                recurisive_vk_codegen::create_verifier_contract_from_template(config, tpl, &tmp);
+            }
+            None => {
+                recurisive_vk_codegen::create_verifier_contract_from_default_template(config, &tmp);
+            }
         }
-    }
+        Ok(())
+    })?;
+    report.artifact("sol", &opts.sol);
     log::info!("Contract saved to {}", opts.sol);
+    Ok(())
 }
 
 // export a verification key for a circuit, and save it to a file
-fn export_vk(opts: ExportVerificationKeyOpts) {
+fn export_vk<E: PlonkEngine>(opts: ExportVerificationKeyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    if opts.backend == Backend::Groth16 {
+        return export_groth16_vk::<E>(opts, report);
+    }
+    let vk_meta = plonk::artifact_meta_file(&opts.vk);
+    check_outputs(opts.overwrite, &[&opts.vk, &vk_meta, &opts.vkeyjson])?;
+    let srs_monomial_form = opts
+        .srs_monomial_form
+        .ok_or_else(|| invalid_input!("--srs_monomial_form is required by the plonk backend"))?;
     let circuit_file = resolve_circuit_file(opts.circuit);
     log::info!("Loading circuit from {}...", circuit_file);
+    let (r1cs, wire_mapping) = reader::load_r1cs_with_wire_mapping::<E>(&circuit_file)?;
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(&circuit_file),
+        r1cs,
         witness: None,
-        wire_mapping: None,
+        wire_mapping,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
     };
+    circuit.r1cs.ensure_no_custom_gates()?;
+    let circuit_digest = circuit.r1cs.digest();
 
-    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit, reader::load_key_monomial_form(&opts.srs_monomial_form), None)
-        .expect("prepare err");
-    let vk: franklin_crypto::bellman::plonk::VerificationKey<Bn256, franklin_crypto::bellman::plonk::better_cs::cs::PlonkCsWidth4WithNextStepParams> = setup.make_verification_key().unwrap();
+    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit, reader::load_key_monomial_form(&srs_monomial_form)?, None)?;
+    let meta = plonk::ArtifactMeta {
+        curve: E::CURVE,
+        transcript: None,
+        circuit_digest,
+        srs_power: setup.srs_power(),
+    };
+    let vk: VerificationKey<E, PlonkCsWidth4WithNextStepParams> = setup.make_verification_key()?;
+    report.detail("num_gates", setup.num_gates());
+    report.detail("srs_power", setup.srs_power());
 
-    let vk_clone = vk.clone();
     let vkey_str = VerificationKeyStr {
-        n: vk_clone.n,
-        num_inputs: vk_clone.num_inputs,
-        selector_commitments: vk_clone.selector_commitments.into_iter().map(|x| hex::encode(G1Uncompressed::from_affine(x))).collect(),
-        next_step_selector_commitments: vk_clone.next_step_selector_commitments.into_iter().map(|x| hex::encode(G1Uncompressed::from_affine(x))).collect(),
-        permutation_commitments: vk_clone.permutation_commitments.into_iter().map(|x| hex::encode(G1Uncompressed::from_affine(x))).collect(),
-        non_residues: vk_clone.non_residues.into_iter().map(|x| to_hex(&x)).collect(),
-        g2_elements: vk_clone.g2_elements.into_iter().map(|x| hex::encode(G2Uncompressed::from_affine(x))).collect(),
+        meta: Some(ArtifactMetaStr::from(&meta)),
+        ..VerificationKeyStr::from(&vk)
     };
 
     // println!("vkey string之后为:{:?}", vkey_str);
     let json_vkey = serde_json::to_string(&vkey_str).unwrap();
+    write_atomically(&opts.vkeyjson, |w| w.write_all(json_vkey.as_bytes()))?;
+    report.artifact("vkeyjson", &opts.vkeyjson);
 
-    if let Err(err) = std::fs::write(opts.vkeyjson, json_vkey) {
-        eprintln!("Error writing file: {}", err);
-    }
-    if !opts.overwrite {
-        let path = Path::new(&opts.vk);
-        assert!(!path.exists(), "duplicate vk file: {}", path.display());
-    }
-    let writer = File::create(&opts.vk).unwrap();
-    vk.write(writer).unwrap();
+    write_atomically(&opts.vk, |w| vk.write(w))?;
+    report.artifact("vk", &opts.vk);
+    log::info!("Verification key saved to {}", opts.vk);
+
+    write_atomically(&vk_meta, |w| meta.write(w))?;
+    report.artifact("vk_meta", &vk_meta);
+    Ok(())
+}
+
+// export the verification key of groth16 parameters, and save it to a file
+fn export_groth16_vk<E: CircomEngine>(opts: ExportVerificationKeyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.vk])?;
+    log::info!("Loading groth16 parameters from {}...", opts.params);
+    let params = reader::load_groth16_parameters::<E>(&opts.params)?;
+    write_atomically(&opts.vk, |w| params.verification_key().write(w))?;
+    report.artifact("vk", &opts.vk);
     log::info!("Verification key saved to {}", opts.vk);
+    Ok(())
+}
+
+// export a proving key for a circuit, and save it to a file
+fn export_pk<E: CircomEngine>(opts: ExportProvingKeyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.proving_key])?;
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    log::info!("Loading circuit from {}...", circuit_file);
+    let (r1cs, wire_mapping) = reader::load_r1cs_with_wire_mapping::<E>(&circuit_file)?;
+    let circuit = CircomCircuit {
+        r1cs,
+        witness: None,
+        wire_mapping,
+        aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
+    };
+    circuit.r1cs.ensure_no_custom_gates()?;
+
+    let digest = circuit.r1cs.digest();
+    let proving_key = report.time("setup", || plonk::ProvingKey::generate(circuit, digest))?;
+    write_atomically(&opts.proving_key, |w| proving_key.write(w))?;
+    report.artifact("proving_key", &opts.proving_key);
+    log::info!("Proving key saved to {}", opts.proving_key);
+    Ok(())
 }
 
 // export a verification key for a recursion circuit, and save it to a file
-fn export_recursive_vk(opts: ExportRecursiveVerificationKeyOpts) {
-    let big_crs = reader::load_key_monomial_form(&opts.srs_monomial_form);
-    let vk =
-        recursive::export_vk(opts.num_proofs_to_check, opts.num_inputs, &big_crs).expect("must create recursive circuit verification key");
-    if !opts.overwrite {
-        let path = Path::new(&opts.vk);
-        assert!(!path.exists(), "duplicate vk file: {}", path.display());
-    }
-    let writer = File::create(&opts.vk).unwrap();
-    vk.write(writer).unwrap();
+fn export_recursive_vk(opts: ExportRecursiveVerificationKeyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    let mut outputs = vec![opts.vk.as_str()];
+    outputs.extend(opts.proving_key.as_deref());
+    check_outputs(opts.overwrite, &outputs)?;
+    let big_crs = reader::load_key_monomial_form(&opts.srs_monomial_form)?;
+    let (vk, proving_key) = report.time("setup", || {
+        recursive::export_vk_and_proving_key(opts.num_proofs_to_check, opts.num_inputs, opts.vk_tree_depth, &big_crs)
+    })?;
+    write_atomically(&opts.vk, |w| {
+        proving_key.meta.write(&mut *w)?;
+        vk.write(w)
+    })?;
+    report.artifact("vk", &opts.vk);
     log::info!("Recursive verification key saved to {}", opts.vk);
+    if let Some(proving_key_file) = &opts.proving_key {
+        write_atomically(proving_key_file, |w| proving_key.write(w))?;
+        report.artifact("proving_key", proving_key_file);
+        log::info!("Recursive proving key saved to {}", proving_key_file);
+    }
+    Ok(())
+}
+
+// the verification keys of the proofs to aggregate, from --old_vk_list if given
+fn load_old_vks(
+    old_vk: &str,
+    old_vk_list: &Option<String>,
+) -> Result<Vec<VerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>>, anyhow::Error> {
+    Ok(match old_vk_list {
+        Some(list) => reader::load_verification_keys_from_list::<Bn256>(list)?,
+        None => vec![reader::load_verification_key::<Bn256>(old_vk)?],
+    })
+}
+
+// the vk tree depth of a recursive artifact, which legacy ones don't record. the meta comes with the file it is read from
+fn resolve_vk_tree_depth(given: Option<usize>, meta: Option<(&recursive::RecursiveMeta, &str)>) -> Result<usize, anyhow::Error> {
+    match (meta.map(|(meta, artifact)| (meta.vk_tree_depth, artifact)), given) {
+        (Some((recorded, artifact)), Some(given)) if recorded != given => {
+            anyhow::bail!(invalid_input!(
+                "{} is made with a vk tree of depth {}, not {}",
+                artifact,
+                recorded,
+                given
+            ))
+        }
+        (recorded, given) => Ok(recorded
+            .map(|(recorded, _)| recorded)
+            .or(given)
+            .unwrap_or(recursive::DEFAULT_VK_TREE_DEPTH)),
+    }
 }
 
 // recursively prove multiple proofs, and aggregate them into one, and save the proof to a file
-fn recursive_prove(opts: RecursiveProveOpts) {
-    let big_crs = reader::load_key_monomial_form(&opts.srs_monomial_form);
-    let old_proofs = reader::load_proofs_from_list::<Bn256>(&opts.old_proof_list);
-    let old_vk = reader::load_verification_key::<Bn256>(&opts.old_vk);
-    let proof = recursive::prove(big_crs, old_proofs, old_vk).unwrap();
-    if !opts.overwrite {
-        let path = Path::new(&opts.new_proof);
-        assert!(!path.exists(), "duplicate proof file: {}", path.display());
-        let path = Path::new(&opts.proofjson);
-        assert!(!path.exists(), "duplicate proof json file: {}", path.display());
-    }
-    let writer = File::create(&opts.new_proof).unwrap();
-    proof.write(writer).unwrap();
+fn recursive_prove(opts: RecursiveProveOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    check_outputs(opts.overwrite, &[&opts.new_proof, &opts.proofjson])?;
+    let big_crs = reader::load_key_monomial_form(&opts.srs_monomial_form)?;
+    let old_proofs = reader::load_proofs_from_list::<Bn256>(&opts.old_proof_list)?;
+    let old_vks = load_old_vks(&opts.old_vk, &opts.old_vk_list)?;
+    let vk_indexes = match opts.vk_indexes.len() {
+        0 => vec![0; old_proofs.len()],
+        _ => opts.vk_indexes.clone(),
+    };
+    let proving_key = match &opts.proving_key {
+        Some(proving_key_file) => {
+            log::info!("Loading recursive proving key from {}...", proving_key_file);
+            Some((reader::load_recursive_proving_key(proving_key_file)?, proving_key_file.as_str()))
+        }
+        None => None,
+    };
+    let meta = proving_key.as_ref().map(|(proving_key, _)| proving_key.meta);
+    let vk_tree_depth = resolve_vk_tree_depth(
+        opts.vk_tree_depth,
+        proving_key.as_ref().map(|(proving_key, file)| (&proving_key.meta, *file)),
+    )?;
+    let num_proofs_to_check = opts
+        .num_proofs_to_check
+        .or_else(|| meta.map(|meta| meta.num_proofs_to_check))
+        .unwrap_or(old_proofs.len());
+    let padding = match &opts.padding_proof {
+        Some(padding_proof) => Some(recursive::Padding {
+            proof: reader::load_proof::<Bn256>(padding_proof)?,
+            vk_index: opts.padding_vk_index,
+        }),
+        None => None,
+    };
+    report.detail("num_proofs", old_proofs.len());
+    report.detail("num_proofs_to_check", num_proofs_to_check);
+    report.detail("num_vks", old_vks.len());
+    let proof = report.time("prove", || {
+        recursive::prove(
+            big_crs,
+            old_proofs,
+            &old_vks,
+            &vk_indexes,
+            num_proofs_to_check,
+            padding.as_ref(),
+            vk_tree_depth,
+            proving_key.as_ref().map(|(proving_key, _)| proving_key),
+        )
+    })?;
+    let meta = recursive::RecursiveMeta::of_proof(&proof, vk_tree_depth);
+    write_atomically(&opts.new_proof, |w| {
+        meta.write(&mut *w)?;
+        proof.write(w)
+    })?;
+    report.artifact("new_proof", &opts.new_proof);
     log::info!("Proof saved to {}", opts.new_proof);
 
     let ser_proof_str = serde_json::to_string_pretty(&proof).unwrap();
-    std::fs::write(&opts.proofjson, ser_proof_str.as_bytes()).expect("save proofjson err");
+    write_atomically(&opts.proofjson, |w| w.write_all(ser_proof_str.as_bytes()))?;
+    report.artifact("proofjson", &opts.proofjson);
     log::info!("Proof json saved to {}", opts.proofjson);
+    Ok(())
 }
 
 // verify a recursive proof by using a corresponding verification key
-fn recursive_verify(opts: RecursiveVerifyOpts) {
-    let vk = reader::load_recursive_verification_key(&opts.vk);
-    let proof = reader::load_aggregated_proof(&opts.proof);
-    let correct = recursive::verify(vk, proof).expect("fail to verify recursive proof");
-    if correct {
-        log::info!("Proof is valid.");
-    } else {
-        log::info!("Proof is invalid!");
-        std::process::exit(400);
+fn recursive_verify(opts: RecursiveVerifyOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    let (vk, vk_meta) = reader::load_recursive_verification_key_with_meta(&opts.vk)?;
+    let (proof, proof_meta) = reader::load_aggregated_proof_with_meta(&opts.proof)?;
+    recursive::check_metas(vk_meta.as_ref(), proof_meta.as_ref())?;
+    let correct = report.time("verify", || recursive::verify(vk, proof))?;
+    report.valid = Some(correct);
+    if !correct {
+        return Err(CheckFailed("Proof is invalid!".to_owned()).into());
     }
+    log::info!("Proof is valid.");
+    Ok(())
 }
 
 // check an aggregated proof is corresponding to the original proofs
-fn check_aggregation(opts: CheckAggregationOpts) {
-    let old_proofs = reader::load_proofs_from_list::<Bn256>(&opts.old_proof_list);
-    let old_vk = reader::load_verification_key::<Bn256>(&opts.old_vk);
-    let new_proof = reader::load_aggregated_proof(&opts.new_proof);
+fn check_aggregation(opts: CheckAggregationOpts, report: &mut Report) -> Result<(), anyhow::Error> {
+    let mut old_proofs = reader::load_proofs_from_list::<Bn256>(&opts.old_proof_list)?;
+    let old_vks = load_old_vks(&opts.old_vk, &opts.old_vk_list)?;
+    let (new_proof, meta) = reader::load_aggregated_proof_with_meta(&opts.new_proof)?;
+    let vk_tree_depth = resolve_vk_tree_depth(opts.vk_tree_depth, meta.as_ref().map(|meta| (meta, opts.new_proof.as_str())))?;
+    // the vk indexes recorded in the aggregated proof include the ones of the padding
+    let num_proofs_to_check = new_proof.individual_vk_idxs.len();
+    if old_proofs.len() < num_proofs_to_check {
+        let padding_proof = opts.padding_proof.as_ref().ok_or_else(|| {
+            invalid_input!(
+                "{} checks {} proofs, but {} lists {}, see --padding_proof",
+                opts.new_proof,
+                num_proofs_to_check,
+                opts.old_proof_list,
+                old_proofs.len()
+            )
+        })?;
+        old_proofs.resize(num_proofs_to_check, reader::load_proof::<Bn256>(padding_proof)?);
+    }
 
-    let expected = recursive::get_aggregated_input(old_proofs, old_vk).expect("fail to get aggregated input");
+    let expected = recursive::get_aggregated_input(old_proofs, &old_vks, &new_proof.individual_vk_idxs, vk_tree_depth)?;
     log::info!("hash to input: {:?}", expected);
     log::info!("new_proof's input: {:?}", new_proof.proof.inputs[0]);
 
-    if expected == new_proof.proof.inputs[0] {
-        log::info!("Aggregation hash input match");
-    } else {
-        log::error!("Aggregation hash input mismatch");
+    report.valid = Some(expected == new_proof.proof.inputs[0]);
+    if expected != new_proof.proof.inputs[0] {
+        return Err(CheckFailed("Aggregation hash input mismatch".to_owned()).into());
+    }
+    log::info!("Aggregation hash input match");
+    Ok(())
+}
+
+// serve prove, verify and analyse requests until killed
+fn serve<E: PlonkEngine>(opts: ServeOpts) -> Result<(), anyhow::Error> {
+    log::info!("Loading srs_monomial_form from {}...", opts.srs_monomial_form);
+    let prover = server::Prover::new(
+        reader::load_key_monomial_form::<E>(&opts.srs_monomial_form)?,
+        server::ProverOptions {
+            root: opts.root.into(),
+            max_cached: opts.max_cached,
+        },
+    )?;
+    let options = server::ServerOptions {
+        address: opts.address,
+        jobs: opts.jobs,
+        queue: opts.queue,
+    };
+    server::serve(prover, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let dir = std::env::temp_dir().join(format!("plonkit_test_exit_code_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let missing = dir.join("missing.wtns");
+        let err = reader::load_witness_from_file::<Bn256>(missing.to_str().unwrap()).unwrap_err();
+        assert_eq!(exit_code(&anyhow::Error::from(err)), EXIT_IO);
+
+        let truncated = dir.join("truncated.wtns");
+        std::fs::write(&truncated, b"wtns\x02\x00").unwrap();
+        let err = reader::load_witness_from_file::<Bn256>(truncated.to_str().unwrap()).unwrap_err();
+        assert_eq!(exit_code(&anyhow::Error::from(err)), EXIT_BAD_INPUT);
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
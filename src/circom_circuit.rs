@@ -6,10 +6,12 @@ use std::collections::BTreeMap;
 use std::str;
 
 use crate::bellman_ce::{
-    pairing::{ff::PrimeField, ff::ScalarEngine, Engine},
+    pairing::{ff::Field, ff::PrimeField, ff::PrimeFieldRepr, ff::ScalarEngine, Engine},
     Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable,
 };
+use sha2::{Digest, Sha256};
 
+use crate::symbols::CircuitSymbols;
 use crate::utils::repr_to_big;
 
 #[derive(Serialize, Deserialize)]
@@ -19,6 +21,8 @@ pub struct CircuitJson {
     pub num_inputs: usize,
     #[serde(rename = "nOutputs")]
     pub num_outputs: usize,
+    #[serde(rename = "nPrvInputs", default)]
+    pub num_prv_inputs: usize,
     #[serde(rename = "nVars")]
     pub num_variables: usize,
 }
@@ -29,12 +33,81 @@ pub type Constraint<E> = (
     Vec<(usize, <E as ScalarEngine>::Fr)>,
 );
 
+// a custom gate template declared by circom 2.x (`pragma custom_templates`)
+#[derive(Clone, Debug)]
+pub struct CustomGate<E: Engine> {
+    pub template_name: String,
+    pub parameters: Vec<E::Fr>,
+}
+
+// an application of a custom gate to a list of wires
+#[derive(Clone, Debug)]
+pub struct CustomGateApplication {
+    pub gate_id: usize,
+    pub signals: Vec<usize>,
+}
+
 #[derive(Clone)]
 pub struct R1CS<E: Engine> {
+    // the constant one, outputs and public inputs
     pub num_inputs: usize,
+    pub num_outputs: usize,
+    pub num_prv_inputs: usize,
     pub num_aux: usize,
     pub num_variables: usize,
     pub constraints: Vec<Constraint<E>>,
+    pub custom_gates: Vec<CustomGate<E>>,
+    pub custom_gates_applied: Vec<CustomGateApplication>,
+}
+
+impl<E: Engine> R1CS<E> {
+    // a sha256 fingerprint of the circuit, used to bind artifacts to the r1cs they were generated from
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(&(self.num_inputs as u64).to_le_bytes());
+        hasher.update(&(self.num_aux as u64).to_le_bytes());
+        hasher.update(&(self.constraints.len() as u64).to_le_bytes());
+        let mut buf = vec![];
+        for constraint in &self.constraints {
+            for lc in &[&constraint.0, &constraint.1, &constraint.2] {
+                hasher.update(&(lc.len() as u64).to_le_bytes());
+                for (index, coeff) in lc.iter() {
+                    hasher.update(&(*index as u64).to_le_bytes());
+                    buf.clear();
+                    coeff.into_repr().write_le(&mut buf).expect("write into a vec must succeed");
+                    hasher.update(&buf);
+                }
+            }
+        }
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.finalize());
+        digest
+    }
+
+    // custom gates are not part of the constraint list, so they cannot be transpiled into plonk gates yet
+    pub fn ensure_no_custom_gates(&self) -> Result<(), anyhow::Error> {
+        if self.custom_gates_applied.is_empty() {
+            return Ok(());
+        }
+        let names = self.custom_gates.iter().map(|g| g.template_name.as_str()).join(", ");
+        anyhow::bail!(invalid_input!(
+            "circuit uses {} custom gate application(s) of [{}], which cannot be lowered into plonk gates. \
+             compile the circuit without `pragma custom_templates`",
+            self.custom_gates_applied.len(),
+            names
+        ))
+    }
+}
+
+// a constraint A * B = C that does not hold for a witness, with the evaluations of its linear combinations
+#[derive(Clone, Debug)]
+pub struct UnsatisfiedConstraint<E: Engine> {
+    pub index: usize,
+    pub a: E::Fr,
+    pub b: E::Fr,
+    pub c: E::Fr,
+    // the wires appearing in the constraint, with their values
+    pub wires: Vec<(usize, E::Fr)>,
 }
 
 #[derive(Clone)]
@@ -44,6 +117,7 @@ pub struct CircomCircuit<E: Engine> {
     pub wire_mapping: Option<Vec<usize>>,
     pub aux_offset: usize,
     // debug symbols
+    pub symbols: Option<CircuitSymbols>,
 }
 
 impl<'a, E: Engine> CircomCircuit<E> {
@@ -57,6 +131,63 @@ impl<'a, E: Engine> CircomCircuit<E> {
         }
     }
 
+    // evaluate every constraint directly over the witness, and return the ones which are not satisfied
+    pub fn unsatisfied_constraints(&self) -> Result<Vec<UnsatisfiedConstraint<E>>, anyhow::Error> {
+        let witness = match &self.witness {
+            None => anyhow::bail!(invalid_input!("no witness to check")),
+            Some(w) => w,
+        };
+        let values: Vec<E::Fr> = match &self.wire_mapping {
+            None => {
+                anyhow::ensure!(
+                    witness.len() >= self.r1cs.num_variables,
+                    invalid_input!(
+                        "witness has {} values, but the circuit has {} wires",
+                        witness.len(),
+                        self.r1cs.num_variables
+                    )
+                );
+                witness[..self.r1cs.num_variables].to_vec()
+            }
+            Some(m) => m.iter().map(|label| witness[*label]).collect(),
+        };
+        anyhow::ensure!(values[0] == E::Fr::one(), invalid_input!("the first witness value must be 1"));
+
+        let eval = |lc: &[(usize, E::Fr)]| {
+            lc.iter().fold(E::Fr::zero(), |mut acc, (index, coeff)| {
+                let mut term = values[*index];
+                term.mul_assign(coeff);
+                acc.add_assign(&term);
+                acc
+            })
+        };
+        let mut result = Vec::new();
+        for (index, (a, b, c)) in self.r1cs.constraints.iter().enumerate() {
+            let (a_value, b_value, c_value) = (eval(a), eval(b), eval(c));
+            let mut ab = a_value;
+            ab.mul_assign(&b_value);
+            if ab != c_value {
+                let wires = a
+                    .iter()
+                    .chain(b)
+                    .chain(c)
+                    .map(|(i, _)| *i)
+                    .sorted()
+                    .dedup()
+                    .map(|i| (i, values[i]))
+                    .collect();
+                result.push(UnsatisfiedConstraint {
+                    index,
+                    a: a_value,
+                    b: b_value,
+                    c: c_value,
+                    wires,
+                });
+            }
+        }
+        Ok(result)
+    }
+
     pub fn get_public_inputs_json(&self) -> String {
         let inputs = self.get_public_inputs();
         let inputs = match inputs {
@@ -65,6 +196,74 @@ impl<'a, E: Engine> CircomCircuit<E> {
         };
         serde_json::to_string_pretty(&inputs).unwrap()
     }
+
+    // public inputs keyed by signal name
+    pub fn get_named_public_inputs_json(&self) -> String {
+        let inputs = match self.get_public_inputs() {
+            None => return String::from("{}"),
+            Some(inp) => inp,
+        };
+        let entries = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (self.wire_name(i + 1), serde_json::Value::String(repr_to_big(x.into_repr()))))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::to_string_pretty(&entries).unwrap()
+    }
+
+    // a readable name of a wire, when symbols are loaded
+    pub fn wire_name(&self, wire: usize) -> String {
+        match &self.symbols {
+            Some(s) => s.wire_name(wire),
+            None => format!("wire {}", wire),
+        }
+    }
+
+    // name a constraint after the most deeply nested signal in it, which is usually in the component generating it
+    pub fn constraint_name(&self, index: usize) -> String {
+        let symbols = match &self.symbols {
+            Some(s) => s,
+            None => return format!("{}", index),
+        };
+        let (a, b, c) = &self.r1cs.constraints[index];
+        a.iter()
+            .chain(b)
+            .chain(c)
+            .filter(|(wire, _)| *wire != 0)
+            .flat_map(|(wire, _)| symbols.wire_names(*wire))
+            .fold(None, |deepest: Option<&str>, name| match deepest {
+                Some(d) if d.matches('.').count() >= name.matches('.').count() => Some(d),
+                _ => Some(name),
+            })
+            .map_or_else(|| format!("{}", index), |name| name.to_string())
+    }
+}
+
+// decide how a witness should be indexed. circom emits witnesses either by label (one value per signal, including
+// the signals removed by the optimizer) or by wire, so the mapping is only kept when the witness is label indexed.
+pub fn wire_mapping_for_witness(
+    wire_mapping: Option<Vec<usize>>,
+    witness_len: usize,
+    num_variables: usize,
+) -> Result<Option<Vec<usize>>, anyhow::Error> {
+    let wire_mapping = match wire_mapping {
+        None => return Ok(None),
+        Some(m) => m,
+    };
+    if witness_len == num_variables || wire_mapping.iter().enumerate().all(|(wire, label)| wire == *label) {
+        return Ok(None);
+    }
+    let max_label = wire_mapping.iter().copied().max().unwrap_or(0);
+    anyhow::ensure!(
+        max_label < witness_len,
+        invalid_input!(
+            "witness has {} values, but the circuit has {} wires and refers to label {}",
+            witness_len,
+            num_variables,
+            max_label
+        )
+    );
+    Ok(Some(wire_mapping))
 }
 
 /// Our demo circuit implements this `Circuit` trait which
@@ -122,7 +321,7 @@ impl<'a, E: Engine> Circuit<E> for CircomCircuit<E> {
             // 0 * LC = 0 must be ignored
             if !((constraint.0.is_empty() || constraint.1.is_empty()) && constraint.2.is_empty()) {
                 cs.enforce(
-                    || format!("{}", i),
+                    || self.constraint_name(i),
                     |_| make_lc(constraint.0.clone()),
                     |_| make_lc(constraint.1.clone()),
                     |_| make_lc(constraint.2.clone()),
@@ -132,3 +331,78 @@ impl<'a, E: Engine> Circuit<E> for CircomCircuit<E> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_mapping_for_witness() {
+        // wire indexed witness needs no mapping
+        assert_eq!(wire_mapping_for_witness(Some(vec![0, 3, 4]), 3, 3).unwrap(), None);
+        // identity mapping is dropped
+        assert_eq!(wire_mapping_for_witness(Some(vec![0, 1, 2]), 5, 3).unwrap(), None);
+        // label indexed witness keeps the mapping
+        assert_eq!(wire_mapping_for_witness(Some(vec![0, 3, 4]), 5, 3).unwrap(), Some(vec![0, 3, 4]));
+        // a witness too short for the labels is rejected
+        assert!(wire_mapping_for_witness(Some(vec![0, 3, 4]), 4, 3).is_err());
+        assert_eq!(wire_mapping_for_witness(None, 4, 3).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unsatisfied_constraints() {
+        use crate::bellman_ce::pairing::bn256::{Bn256, Fr};
+        let fr = |x: &str| Fr::from_str(x).unwrap();
+        // w1 * w2 = w3
+        let mut circuit = CircomCircuit::<Bn256> {
+            r1cs: R1CS {
+                num_inputs: 1,
+                num_outputs: 0,
+                num_prv_inputs: 0,
+                num_aux: 3,
+                num_variables: 4,
+                constraints: vec![(vec![(1, fr("1"))], vec![(2, fr("1"))], vec![(3, fr("1"))])],
+                custom_gates: vec![],
+                custom_gates_applied: vec![],
+            },
+            witness: Some(vec![fr("1"), fr("2"), fr("3"), fr("6")]),
+            wire_mapping: None,
+            aux_offset: 0,
+            symbols: None,
+        };
+        assert!(circuit.unsatisfied_constraints().unwrap().is_empty());
+
+        circuit.witness = Some(vec![fr("1"), fr("2"), fr("3"), fr("7")]);
+        let unsatisfied = circuit.unsatisfied_constraints().unwrap();
+        assert_eq!(unsatisfied.len(), 1);
+        assert_eq!((unsatisfied[0].a, unsatisfied[0].b, unsatisfied[0].c), (fr("2"), fr("3"), fr("7")));
+        assert_eq!(unsatisfied[0].wires.iter().map(|(i, _)| *i).collect_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_symbol_names() {
+        use crate::bellman_ce::pairing::bn256::{Bn256, Fr};
+        let fr = |x: &str| Fr::from_str(x).unwrap();
+        let sym = "1,1,0,main.out\n2,2,0,main.a\n3,3,1,main.mul.out\n";
+        // w1 = w2 * w3
+        let circuit = CircomCircuit::<Bn256> {
+            r1cs: R1CS {
+                num_inputs: 2,
+                num_outputs: 1,
+                num_prv_inputs: 2,
+                num_aux: 2,
+                num_variables: 4,
+                constraints: vec![(vec![(2, fr("1"))], vec![(3, fr("1"))], vec![(1, fr("1"))])],
+                custom_gates: vec![],
+                custom_gates_applied: vec![],
+            },
+            witness: Some(vec![fr("1"), fr("6"), fr("2"), fr("3")]),
+            wire_mapping: None,
+            aux_offset: 0,
+            symbols: Some(CircuitSymbols::from_reader(sym.as_bytes()).unwrap()),
+        };
+        assert_eq!(circuit.constraint_name(0), "main.mul.out");
+        let named: serde_json::Value = serde_json::from_str(&circuit.get_named_public_inputs_json()).unwrap();
+        assert_eq!(named, serde_json::json!({"main.out": "6"}));
+    }
+}
@@ -0,0 +1,103 @@
+// the curves plonkit works with. circom records which one a circuit is compiled for by the prime of the scalar field,
+// in the headers of r1cs and wtns files
+use crate::bellman_ce::pairing::{bls12_381::Bls12, bn256::Bn256, Engine};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Bn256,
+    Bls12_381,
+}
+
+impl Curve {
+    // the scalar field prime in little endian, as circom writes it
+    pub fn prime(&self) -> &'static [u8; 32] {
+        match self {
+            Curve::Bn256 => &<Bn256 as CircomEngine>::PRIME,
+            Curve::Bls12_381 => &<Bls12 as CircomEngine>::PRIME,
+        }
+    }
+
+    pub fn from_prime(prime: &[u8]) -> Option<Self> {
+        [Curve::Bn256, Curve::Bls12_381].iter().copied().find(|c| &c.prime()[..] == prime)
+    }
+
+    // the identifier in artifact headers
+    pub fn id(&self) -> u8 {
+        match self {
+            Curve::Bn256 => 0,
+            Curve::Bls12_381 => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Curve::Bn256),
+            1 => Some(Curve::Bls12_381),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Curve {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bn256" | "bn254" | "bn128" => Ok(Curve::Bn256),
+            "bls12_381" | "bls12-381" => Ok(Curve::Bls12_381),
+            _ => anyhow::bail!(invalid_input!("unknown curve {}, expected bn256 or bls12_381", s)),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Curve::Bn256 => write!(f, "bn256"),
+            Curve::Bls12_381 => write!(f, "bls12_381"),
+        }
+    }
+}
+
+// an engine whose scalar field circom can compile circuits for
+pub trait CircomEngine: Engine {
+    const CURVE: Curve;
+    // the scalar field prime in little endian
+    const PRIME: [u8; 32];
+}
+
+impl CircomEngine for Bn256 {
+    const CURVE: Curve = Curve::Bn256;
+    const PRIME: [u8; 32] = hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430");
+}
+
+impl CircomEngine for Bls12 {
+    const CURVE: Curve = Curve::Bls12_381;
+    const PRIME: [u8; 32] = hex!("01000000fffffffffe5bfeff02a4bd5305d8a10908d83933487d9d2953a7ed73");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::ff::{PrimeField, PrimeFieldRepr};
+
+    #[test]
+    fn test_primes() {
+        fn le_char<E: Engine>() -> Vec<u8> {
+            let mut buf = vec![];
+            E::Fr::char().write_le(&mut buf).unwrap();
+            buf
+        }
+        assert_eq!(le_char::<Bn256>(), Bn256::PRIME.to_vec());
+        assert_eq!(le_char::<Bls12>(), Bls12::PRIME.to_vec());
+        assert_eq!(Curve::from_prime(&Bls12::PRIME), Some(Curve::Bls12_381));
+        assert_eq!(Curve::from_prime(&[0u8; 32]), None);
+        for curve in &[Curve::Bn256, Curve::Bls12_381] {
+            assert_eq!(curve.to_string().parse::<Curve>().unwrap(), *curve);
+            assert_eq!(Curve::from_id(curve.id()), Some(*curve));
+        }
+    }
+}
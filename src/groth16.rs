@@ -0,0 +1,75 @@
+// groth16 proofs of the same circom circuits, with a circuit specific setup generated locally (development only)
+use crate::bellman_ce::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof, Parameters};
+use crate::bellman_ce::{Circuit, SynthesisError};
+use crate::curve::{CircomEngine, Curve};
+
+use std::io::{Read, Write};
+
+pub use crate::bellman_ce::groth16::{Proof, VerifyingKey};
+
+// unlike the plonk transpiler, the groth16 constraint system doesn't reserve the first aux variable
+pub const AUX_OFFSET: usize = 0;
+
+const PARAMETERS_MAGIC: &[u8; 4] = b"g16p";
+
+// the proving parameters of a circuit, which also contain its verifying key
+pub struct ProvingParameters<E: CircomEngine> {
+    pub circuit_digest: [u8; 32],
+    pub params: Parameters<E>,
+}
+
+impl<E: CircomEngine> ProvingParameters<E> {
+    // run the circuit specific setup, the toxic waste is sampled locally and dropped
+    pub fn generate<C: Circuit<E>>(circuit: C, circuit_digest: [u8; 32]) -> Result<Self, SynthesisError> {
+        let rng = &mut rand::thread_rng();
+        let params = generate_random_parameters::<E, _, _>(circuit, rng)?;
+        Ok(ProvingParameters { circuit_digest, params })
+    }
+
+    pub fn verification_key(&self) -> &VerifyingKey<E> {
+        &self.params.vk
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(PARAMETERS_MAGIC)?;
+        writer.write_all(&[E::CURVE.id()])?;
+        writer.write_all(&self.circuit_digest)?;
+        self.params.write(writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let curve = read_curve(&mut reader)?;
+        if curve != E::CURVE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("groth16 parameters are generated for the {} curve, not {}", curve, E::CURVE),
+            ));
+        }
+        let mut circuit_digest = [0u8; 32];
+        reader.read_exact(&mut circuit_digest)?;
+        let params = Parameters::read(reader, false)?;
+        Ok(ProvingParameters { circuit_digest, params })
+    }
+}
+
+// read the header of groth16 parameters up to the curve they are generated for
+pub fn read_curve<R: Read>(mut reader: R) -> std::io::Result<Curve> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_owned());
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != PARAMETERS_MAGIC {
+        return Err(invalid("invalid groth16 parameters magic"));
+    }
+    Curve::from_id(header[4]).ok_or_else(|| invalid("unknown groth16 parameters curve"))
+}
+
+pub fn prove<E: CircomEngine, C: Circuit<E>>(circuit: C, params: &ProvingParameters<E>) -> Result<Proof<E>, SynthesisError> {
+    let rng = &mut rand::thread_rng();
+    create_random_proof(circuit, &params.params, rng)
+}
+
+// the public inputs don't include the constant one
+pub fn verify<E: CircomEngine>(vk: &VerifyingKey<E>, proof: &Proof<E>, public_inputs: &[E::Fr]) -> Result<bool, SynthesisError> {
+    let pvk = prepare_verifying_key(vk);
+    verify_proof(&pvk, proof, public_inputs)
+}
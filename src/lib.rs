@@ -12,20 +12,83 @@ extern crate num_bigint;
 extern crate num_traits;
 extern crate rand;
 
+// an error about what plonkit is given, such as arguments, inputs or the contents of files, rather than an internal error
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct InvalidInput(pub String);
+
+// like anyhow::format_err!, for invalid input
+#[macro_export]
+macro_rules! invalid_input {
+    ($($arg:tt)*) => {
+        ::anyhow::Error::new($crate::InvalidInput(format!($($arg)*)))
+    };
+}
+
 pub mod circom_circuit;
+pub mod curve;
+pub mod groth16;
 pub mod plonk;
+pub mod ptau_file;
 pub mod r1cs_file;
 pub mod reader;
 pub mod recursive;
+pub mod server;
+pub mod symbols;
 pub mod transpile;
+pub mod underconstrained;
 pub mod utils;
+pub mod witness;
 
+use bellman_ce::{
+    bn256::Bn256,
+    pairing::{
+        ff::{from_hex, to_hex, PrimeField, PrimeFieldRepr},
+        CurveAffine, Engine,
+    },
+    plonk::better_cs::{
+        cs::{PlonkConstraintSystemParams, PlonkCsWidth4WithNextStepParams},
+        keys::{Proof, VerificationKey},
+    },
+    EncodedPoint,
+};
+use byteorder::{BigEndian, WriteBytesExt};
 pub use franklin_crypto::bellman as bellman_ce;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 #[cfg(test)]
 mod tests;
 
+// the proof system used to prove and verify circuits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Plonk,
+    Groth16,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plonk" => Ok(Backend::Plonk),
+            "groth16" => Ok(Backend::Groth16),
+            _ => anyhow::bail!(invalid_input!("unknown backend {}, expected plonk or groth16", s)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Plonk => write!(f, "plonk"),
+            Backend::Groth16 => write!(f, "groth16"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProofStr {
     pub num_inputs: usize,
@@ -42,6 +105,8 @@ pub struct ProofStr {
     pub permutation_polynomials_at_z: Vec<String>,
     pub opening_at_z_proof: String,
     pub opening_at_z_omega_proof: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ArtifactMetaStr>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -53,4 +118,205 @@ pub struct VerificationKeyStr {
     pub permutation_commitments: Vec<String>,
     pub non_residues: Vec<String>,
     pub g2_elements: Vec<String>,
-}
\ No newline at end of file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ArtifactMetaStr>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Groth16ProofStr {
+    pub a: String,
+    pub b: String,
+    pub c: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArtifactMetaStr {
+    pub curve: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<String>,
+    pub circuit_digest: String,
+    pub srs_power: u32,
+}
+
+impl From<&plonk::ArtifactMeta> for ArtifactMetaStr {
+    fn from(meta: &plonk::ArtifactMeta) -> Self {
+        ArtifactMetaStr {
+            curve: meta.curve.to_string(),
+            transcript: meta.transcript.map(|t| t.to_string()),
+            circuit_digest: hex::encode(meta.circuit_digest),
+            srs_power: meta.srs_power,
+        }
+    }
+}
+
+impl TryFrom<&ArtifactMetaStr> for plonk::ArtifactMeta {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &ArtifactMetaStr) -> Result<Self, Self::Error> {
+        let mut circuit_digest = [0u8; 32];
+        hex::decode_to_slice(&s.circuit_digest, &mut circuit_digest)
+            .map_err(|e| invalid_input!("invalid circuit digest {}: {}", s.circuit_digest, e))?;
+        Ok(plonk::ArtifactMeta {
+            curve: s.curve.parse()?,
+            transcript: s.transcript.as_deref().map(str::parse).transpose()?,
+            circuit_digest,
+            srs_power: s.srs_power,
+        })
+    }
+}
+
+const STATE_WIDTH: usize = <PlonkCsWidth4WithNextStepParams as PlonkConstraintSystemParams<Bn256>>::STATE_WIDTH;
+
+type G1Encoding<E> = <<E as Engine>::G1Affine as CurveAffine>::Uncompressed;
+type G2Encoding<E> = <<E as Engine>::G2Affine as CurveAffine>::Uncompressed;
+
+fn encode_point<G: CurveAffine>(p: &G) -> String {
+    hex::encode(p.into_uncompressed())
+}
+
+fn decode_fr<F: PrimeField>(s: &str) -> Result<F, anyhow::Error> {
+    from_hex::<F>(s).map_err(|e| invalid_input!("invalid field element {}: {}", s, e))
+}
+
+// decode a hex encoded uncompressed point, checking it is on the curve and in the subgroup
+fn decode_point<P: EncodedPoint>(s: &str) -> Result<P::Affine, anyhow::Error> {
+    let bytes = hex::decode(s).map_err(|e| invalid_input!("invalid point {}: {}", s, e))?;
+    anyhow::ensure!(
+        bytes.len() == P::size(),
+        invalid_input!("invalid point {}: expected {} bytes", s, P::size())
+    );
+    let mut encoded = P::empty();
+    encoded.as_mut().copy_from_slice(&bytes);
+    encoded.into_affine().map_err(|e| invalid_input!("invalid point {}: {}", s, e))
+}
+
+fn decode_vec<T>(what: &str, values: &[String], len: usize, decode: fn(&str) -> Result<T, anyhow::Error>) -> Result<Vec<T>, anyhow::Error> {
+    anyhow::ensure!(
+        values.len() == len,
+        invalid_input!("expected {} {}, got {}", len, what, values.len())
+    );
+    values.iter().map(|v| decode(v)).collect()
+}
+
+impl<E: Engine> From<&Proof<E, PlonkCsWidth4WithNextStepParams>> for ProofStr {
+    fn from(proof: &Proof<E, PlonkCsWidth4WithNextStepParams>) -> Self {
+        ProofStr {
+            num_inputs: proof.num_inputs,
+            n: proof.n,
+            input_values: proof.input_values.iter().map(to_hex).collect(),
+            wire_commitments: proof.wire_commitments.iter().map(encode_point).collect(),
+            grand_product_commitment: encode_point(&proof.grand_product_commitment),
+            quotient_poly_commitments: proof.quotient_poly_commitments.iter().map(encode_point).collect(),
+            wire_values_at_z: proof.wire_values_at_z.iter().map(to_hex).collect(),
+            wire_values_at_z_omega: proof.wire_values_at_z_omega.iter().map(to_hex).collect(),
+            grand_product_at_z_omega: to_hex(&proof.grand_product_at_z_omega),
+            quotient_polynomial_at_z: to_hex(&proof.quotient_polynomial_at_z),
+            linearization_polynomial_at_z: to_hex(&proof.linearization_polynomial_at_z),
+            permutation_polynomials_at_z: proof.permutation_polynomials_at_z.iter().map(to_hex).collect(),
+            opening_at_z_proof: encode_point(&proof.opening_at_z_proof),
+            opening_at_z_omega_proof: encode_point(&proof.opening_at_z_omega_proof),
+            meta: None,
+        }
+    }
+}
+
+impl<E: Engine> TryFrom<ProofStr> for Proof<E, PlonkCsWidth4WithNextStepParams> {
+    type Error = anyhow::Error;
+
+    fn try_from(s: ProofStr) -> Result<Self, Self::Error> {
+        let mut proof = Proof::empty();
+        proof.num_inputs = s.num_inputs;
+        proof.n = s.n;
+        proof.input_values = decode_vec("input values", &s.input_values, s.num_inputs, decode_fr)?;
+        proof.wire_commitments = decode_vec("wire commitments", &s.wire_commitments, STATE_WIDTH, decode_point::<G1Encoding<E>>)?;
+        proof.grand_product_commitment = decode_point::<G1Encoding<E>>(&s.grand_product_commitment)?;
+        proof.quotient_poly_commitments = decode_vec(
+            "quotient poly commitments",
+            &s.quotient_poly_commitments,
+            STATE_WIDTH,
+            decode_point::<G1Encoding<E>>,
+        )?;
+        proof.wire_values_at_z = decode_vec("wire values at z", &s.wire_values_at_z, STATE_WIDTH, decode_fr)?;
+        proof.wire_values_at_z_omega = decode_vec("wire values at z omega", &s.wire_values_at_z_omega, 1, decode_fr)?;
+        proof.grand_product_at_z_omega = decode_fr(&s.grand_product_at_z_omega)?;
+        proof.quotient_polynomial_at_z = decode_fr(&s.quotient_polynomial_at_z)?;
+        proof.linearization_polynomial_at_z = decode_fr(&s.linearization_polynomial_at_z)?;
+        proof.permutation_polynomials_at_z = decode_vec(
+            "permutation polynomials at z",
+            &s.permutation_polynomials_at_z,
+            STATE_WIDTH - 1,
+            decode_fr,
+        )?;
+        proof.opening_at_z_proof = decode_point::<G1Encoding<E>>(&s.opening_at_z_proof)?;
+        proof.opening_at_z_omega_proof = decode_point::<G1Encoding<E>>(&s.opening_at_z_omega_proof)?;
+        Ok(proof)
+    }
+}
+
+impl<E: Engine> From<&VerificationKey<E, PlonkCsWidth4WithNextStepParams>> for VerificationKeyStr {
+    fn from(vk: &VerificationKey<E, PlonkCsWidth4WithNextStepParams>) -> Self {
+        VerificationKeyStr {
+            n: vk.n,
+            num_inputs: vk.num_inputs,
+            selector_commitments: vk.selector_commitments.iter().map(encode_point).collect(),
+            next_step_selector_commitments: vk.next_step_selector_commitments.iter().map(encode_point).collect(),
+            permutation_commitments: vk.permutation_commitments.iter().map(encode_point).collect(),
+            non_residues: vk.non_residues.iter().map(to_hex).collect(),
+            g2_elements: vk.g2_elements.iter().map(encode_point).collect(),
+            meta: None,
+        }
+    }
+}
+
+// the verification key has private fields, so it's decoded into bellman's binary encoding and read from there
+impl<E: Engine> TryFrom<VerificationKeyStr> for VerificationKey<E, PlonkCsWidth4WithNextStepParams> {
+    type Error = anyhow::Error;
+
+    fn try_from(s: VerificationKeyStr) -> Result<Self, Self::Error> {
+        fn write_points<E: Engine>(buf: &mut Vec<u8>, what: &str, points: &[String], len: usize) -> Result<(), anyhow::Error> {
+            buf.write_u64::<BigEndian>(len as u64)?;
+            for p in decode_vec(what, points, len, decode_point::<G1Encoding<E>>)? {
+                buf.extend_from_slice(p.into_uncompressed().as_ref());
+            }
+            Ok(())
+        }
+
+        let mut buf = vec![];
+        buf.write_u64::<BigEndian>(s.n as u64)?;
+        buf.write_u64::<BigEndian>(s.num_inputs as u64)?;
+        // q_a, q_b, q_c, q_d, q_m, q_const
+        write_points::<E>(&mut buf, "selector commitments", &s.selector_commitments, STATE_WIDTH + 2)?;
+        write_points::<E>(&mut buf, "next step selector commitments", &s.next_step_selector_commitments, 1)?;
+        write_points::<E>(&mut buf, "permutation commitments", &s.permutation_commitments, STATE_WIDTH)?;
+        buf.write_u64::<BigEndian>((STATE_WIDTH - 1) as u64)?;
+        for x in decode_vec("non residues", &s.non_residues, STATE_WIDTH - 1, decode_fr::<E::Fr>)? {
+            x.into_repr().write_be(&mut buf)?;
+        }
+        for p in decode_vec("g2 elements", &s.g2_elements, 2, decode_point::<G2Encoding<E>>)? {
+            buf.extend_from_slice(p.into_uncompressed().as_ref());
+        }
+        Ok(VerificationKey::read(&buf[..])?)
+    }
+}
+
+impl<E: Engine> From<&groth16::Proof<E>> for Groth16ProofStr {
+    fn from(proof: &groth16::Proof<E>) -> Self {
+        Groth16ProofStr {
+            a: encode_point(&proof.a),
+            b: encode_point(&proof.b),
+            c: encode_point(&proof.c),
+        }
+    }
+}
+
+impl<E: Engine> TryFrom<Groth16ProofStr> for groth16::Proof<E> {
+    type Error = anyhow::Error;
+
+    fn try_from(s: Groth16ProofStr) -> Result<Self, Self::Error> {
+        Ok(groth16::Proof {
+            a: decode_point::<G1Encoding<E>>(&s.a)?,
+            b: decode_point::<G2Encoding<E>>(&s.b)?,
+            c: decode_point::<G1Encoding<E>>(&s.c)?,
+        })
+    }
+}
@@ -1,22 +1,31 @@
 // Most of this file is forked from source codes of [Matter Labs's zkSync](https://github.com/matter-labs/zksync)
-use crate::bellman_ce::bn256::Bn256;
 use crate::bellman_ce::{
+    bls12_381::Bls12,
+    bn256::Bn256,
     kate_commitment::{Crs, CrsForLagrangeForm, CrsForMonomialForm},
-    pairing::Engine,
+    pairing::{ff::PrimeField, CurveAffine, CurveProjective, Engine},
     plonk::{
-        better_cs::adaptor::TranspilationVariant,
+        better_cs::adaptor::{read_transpilation_hints, write_transpilation_hints, TranspilationVariant},
         better_cs::cs::PlonkCsWidth4WithNextStepParams,
         better_cs::keys::{Proof, SetupPolynomials, VerificationKey},
+        better_cs::verifier,
         commitments::transcript::keccak_transcript::RollingKeccakTranscript,
         is_satisfied_using_one_shot_check, make_verification_key, prove, prove_by_steps, setup,
     },
     worker::Worker,
-    Circuit, ScalarEngine, SynthesisError,
+    Circuit, Field, ScalarEngine, SynthesisError,
 };
 use crate::circom_circuit::CircomCircuit;
+use crate::curve::{CircomEngine, Curve};
 use crate::transpile::{transpile_with_gates_count, ConstraintStat, TranspilerWrapper};
 
-type E = Bn256;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
 use franklin_crypto::plonk::circuit::bigint::field::RnsParameters;
 use franklin_crypto::rescue::rescue_transcript::RescueTranscriptForRNS;
 use franklin_crypto::rescue::RescueEngine;
@@ -27,10 +36,10 @@ const SETUP_MIN_POW2: u32 = 10;
 const SETUP_MAX_POW2: u32 = 26;
 
 // generate a monomial_form SRS
-pub fn gen_key_monomial_form(power: u32) -> Result<Crs<E, CrsForMonomialForm>, anyhow::Error> {
+pub fn gen_key_monomial_form<E: Engine>(power: u32) -> Result<Crs<E, CrsForMonomialForm>, anyhow::Error> {
     anyhow::ensure!(
         (SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&power),
-        "setup power of two is not in the correct range"
+        invalid_input!("setup power of two is not in the correct range")
     );
 
     // run a small setup to estimate time
@@ -47,7 +56,208 @@ pub fn gen_key_monomial_form(power: u32) -> Result<Crs<E, CrsForMonomialForm>, a
     Ok(Crs::<E, CrsForMonomialForm>::crs_42(1 << power, &Worker::new()))
 }
 
-pub struct SetupForProver {
+// the Fiat-Shamir transcript used by the prover and the verifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transcript {
+    Keccak,
+    Rescue,
+}
+
+impl FromStr for Transcript {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keccak" => Ok(Transcript::Keccak),
+            "rescue" => Ok(Transcript::Rescue),
+            _ => anyhow::bail!(invalid_input!("invalid transcript {:?}. use 'keccak' or 'rescue'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transcript::Keccak => write!(f, "keccak"),
+            Transcript::Rescue => write!(f, "rescue"),
+        }
+    }
+}
+
+const PROVING_KEY_MAGIC: &[u8; 4] = b"pkey";
+
+// the circuit specific part of a SetupForProver, which can be saved to skip transpilation and setup
+pub struct ProvingKey<E: Engine> {
+    pub circuit_digest: [u8; 32],
+    pub setup_polynomials: SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    pub hints: Vec<(usize, TranspilationVariant)>,
+}
+
+#[allow(clippy::ptr_arg)]
+fn write_proving_key<E: Engine, W: Write>(
+    circuit_digest: &[u8; 32],
+    setup_polynomials: &SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    hints: &Vec<(usize, TranspilationVariant)>,
+    mut writer: W,
+) -> std::io::Result<()> {
+    writer.write_all(PROVING_KEY_MAGIC)?;
+    writer.write_all(circuit_digest)?;
+    setup_polynomials.write(&mut writer)?;
+    write_transpilation_hints(hints, &mut writer)
+}
+
+impl<E: Engine> ProvingKey<E> {
+    // transpile a circuit and compute its setup polynomials
+    pub fn generate<C: Circuit<E> + Clone>(circuit: C, circuit_digest: [u8; 32]) -> Result<Self, anyhow::Error> {
+        let (gates_count, hints) = transpile_with_gates_count(circuit.clone())?;
+        log::info!("transpile done, gates_count {} hints size {}", gates_count, hints.len());
+        let setup_polynomials = setup(circuit, &hints)?;
+        Ok(ProvingKey {
+            circuit_digest,
+            setup_polynomials,
+            hints,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        write_proving_key(&self.circuit_digest, &self.setup_polynomials, &self.hints, writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != PROVING_KEY_MAGIC {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid proving key magic"));
+        }
+        let mut circuit_digest = [0u8; 32];
+        reader.read_exact(&mut circuit_digest)?;
+        let setup_polynomials = SetupPolynomials::<E, PlonkCsWidth4WithNextStepParams>::read(&mut reader)?;
+        let hints = read_transpilation_hints(&mut reader)?;
+        Ok(ProvingKey {
+            circuit_digest,
+            setup_polynomials,
+            hints,
+        })
+    }
+}
+
+const ARTIFACT_META_MAGIC: &[u8; 4] = b"pkmt";
+const ARTIFACT_META_VERSION: u8 = 1;
+
+// how a proof or verification key BIN file was generated. it is saved in a file next to it, so that the BIN file
+// stays as bellman writes it for the tools which read it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArtifactMeta {
+    pub curve: Curve,
+    // verification keys don't depend on the transcript
+    pub transcript: Option<Transcript>,
+    pub circuit_digest: [u8; 32],
+    pub srs_power: u32,
+}
+
+impl ArtifactMeta {
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(ARTIFACT_META_MAGIC)?;
+        writer.write_u8(ARTIFACT_META_VERSION)?;
+        writer.write_u8(match self.transcript {
+            None => 0,
+            Some(Transcript::Keccak) => 1,
+            Some(Transcript::Rescue) => 2,
+        })?;
+        writer.write_u8(self.curve.id())?;
+        writer.write_all(&self.circuit_digest)?;
+        writer.write_u32::<LittleEndian>(self.srs_power)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != ARTIFACT_META_MAGIC {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid artifact meta magic"));
+        }
+        let version = reader.read_u8()?;
+        if version != ARTIFACT_META_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported artifact meta version {}", version),
+            ));
+        }
+        let transcript = match reader.read_u8()? {
+            0 => None,
+            1 => Some(Transcript::Keccak),
+            2 => Some(Transcript::Rescue),
+            t => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown transcript {}", t),
+                ))
+            }
+        };
+        let id = reader.read_u8()?;
+        let curve =
+            Curve::from_id(id).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown curve {}", id)))?;
+        let mut circuit_digest = [0u8; 32];
+        reader.read_exact(&mut circuit_digest)?;
+        let srs_power = reader.read_u32::<LittleEndian>()?;
+        Ok(ArtifactMeta {
+            curve,
+            transcript,
+            circuit_digest,
+            srs_power,
+        })
+    }
+}
+
+// the file the meta of a proof or verification key BIN file is saved to
+pub fn artifact_meta_file(filename: &str) -> String {
+    format!("{}.meta", filename)
+}
+
+// check that a proof and a verification key belong together, and pick the transcript to verify with
+pub fn check_artifacts(
+    transcript: Option<Transcript>,
+    vk_meta: Option<&ArtifactMeta>,
+    proof_meta: Option<&ArtifactMeta>,
+) -> Result<Transcript, anyhow::Error> {
+    if let (Some(vk_meta), Some(proof_meta)) = (vk_meta, proof_meta) {
+        anyhow::ensure!(
+            vk_meta.curve == proof_meta.curve,
+            invalid_input!(
+                "the proof is generated on {}, but the verification key on {}",
+                proof_meta.curve,
+                vk_meta.curve
+            )
+        );
+        anyhow::ensure!(
+            vk_meta.circuit_digest == proof_meta.circuit_digest,
+            invalid_input!(
+                "the proof is generated for circuit {}, but the verification key is exported from circuit {}",
+                hex::encode(proof_meta.circuit_digest),
+                hex::encode(vk_meta.circuit_digest)
+            )
+        );
+        if vk_meta.srs_power != proof_meta.srs_power {
+            log::warn!(
+                "the proof is generated with a SRS of power {}, but the verification key with power {}",
+                proof_meta.srs_power,
+                vk_meta.srs_power
+            );
+        }
+    }
+    match (transcript, proof_meta.and_then(|m| m.transcript)) {
+        (Some(requested), Some(recorded)) if requested != recorded => {
+            anyhow::bail!(invalid_input!(
+                "the proof is generated with the {} transcript, but {} is requested",
+                recorded,
+                requested
+            ))
+        }
+        (Some(transcript), _) | (None, Some(transcript)) => Ok(transcript),
+        (None, None) => Ok(Transcript::Keccak),
+    }
+}
+
+pub struct SetupForProver<E: Engine> {
     setup_polynomials: SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
     hints: Vec<(usize, TranspilationVariant)>,
     key_monomial_form: Crs<E, CrsForMonomialForm>,
@@ -66,6 +276,70 @@ pub struct AnalyseResult {
     pub num_hints: usize,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub constraint_stats: Vec<ConstraintStat>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub custom_gate_stats: Vec<CustomGateStat>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub component_stats: Vec<ComponentStat>,
+}
+
+// usage of a circom custom gate template
+#[derive(serde::Serialize)]
+pub struct CustomGateStat {
+    pub template_name: String,
+    pub num_applications: usize,
+}
+
+// cost of a circuit component, including its subcomponents
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ComponentStat {
+    pub path: String,
+    pub num_constraints: usize,
+    pub num_gates: usize,
+}
+
+// constraints are named after their most nested signal when the circuit symbols are loaded,
+// so the component is the signal path without the signal name
+fn component_path(constraint_name: &str) -> &str {
+    match constraint_name.rsplit_once('.') {
+        Some((component, _)) => component,
+        None => "<unnamed>",
+    }
+}
+
+// aggregate the constraint stats by the component hierarchy, the most costly first
+pub fn component_stats(constraint_stats: &[ConstraintStat]) -> Vec<ComponentStat> {
+    let mut totals = BTreeMap::<&str, (usize, usize)>::new();
+    for stat in constraint_stats {
+        let path = component_path(&stat.name);
+        let parents = path.match_indices('.').map(|(i, _)| &path[..i]);
+        for component in parents.chain(std::iter::once(path)) {
+            let total = totals.entry(component).or_default();
+            total.0 += 1;
+            total.1 += stat.num_gates;
+        }
+    }
+    let mut stats: Vec<ComponentStat> = totals
+        .into_iter()
+        .map(|(path, (num_constraints, num_gates))| ComponentStat {
+            path: path.to_string(),
+            num_constraints,
+            num_gates,
+        })
+        .collect();
+    stats.sort_by(|a, b| b.num_gates.cmp(&a.num_gates).then_with(|| a.path.cmp(&b.path)));
+    stats
+}
+
+// the gates of each component in the folded stack format (`main;hasher;mix 42`), which flamegraph tools render
+pub fn folded_stacks(constraint_stats: &[ConstraintStat]) -> String {
+    let mut stacks = BTreeMap::<String, usize>::new();
+    for stat in constraint_stats {
+        *stacks.entry(component_path(&stat.name).replace('.', ";")).or_default() += stat.num_gates;
+    }
+    stacks
+        .into_iter()
+        .map(|(stack, num_gates)| format!("{} {}\n", stack, num_gates))
+        .collect()
 }
 
 // analyse a circuit
@@ -80,19 +354,35 @@ pub fn analyse<E: Engine>(circuit: CircomCircuit<E>) -> Result<AnalyseResult, an
         num_gates: 0,
         num_hints: 0,
         constraint_stats: Vec::new(),
+        custom_gate_stats: Vec::new(),
+        component_stats: Vec::new(),
     };
+    let has_symbols = circuit.symbols.is_some();
+    result.custom_gate_stats = circuit
+        .r1cs
+        .custom_gates
+        .iter()
+        .enumerate()
+        .map(|(id, gate)| CustomGateStat {
+            template_name: gate.template_name.clone(),
+            num_applications: circuit.r1cs.custom_gates_applied.iter().filter(|a| a.gate_id == id).count(),
+        })
+        .collect();
     circuit
         .synthesize(&mut transpiler)
         .expect("sythesize into traspilation must succeed");
     result.num_nontrivial_constraints = transpiler.constraint_stats.len();
     result.num_gates = transpiler.num_gates();
     result.constraint_stats = transpiler.constraint_stats.clone();
+    if has_symbols {
+        result.component_stats = component_stats(&result.constraint_stats);
+    }
     let hints = transpiler.into_hints();
     result.num_hints = hints.len();
     Ok(result)
 }
 
-impl SetupForProver {
+impl<E: PlonkEngine> SetupForProver<E> {
     // meta-data preparation before proving a circuit
     pub fn prepare_setup_for_prover<C: Circuit<E> + Clone>(
         circuit: C,
@@ -102,12 +392,35 @@ impl SetupForProver {
         let (gates_count, hints) = transpile_with_gates_count(circuit.clone())?;
         log::info!("transpile done, gates_count {} hints size {}", gates_count, hints.len());
         let setup_polynomials = setup(circuit, &hints)?;
+        Self::from_parts(setup_polynomials, hints, key_monomial_form, key_lagrange_form)
+    }
+
+    // meta-data preparation from a previously exported proving key
+    pub fn prepare_setup_for_prover_from_proving_key(
+        proving_key: ProvingKey<E>,
+        key_monomial_form: Crs<E, CrsForMonomialForm>,
+        key_lagrange_form: Option<Crs<E, CrsForLagrangeForm>>,
+    ) -> Result<Self, anyhow::Error> {
+        Self::from_parts(
+            proving_key.setup_polynomials,
+            proving_key.hints,
+            key_monomial_form,
+            key_lagrange_form,
+        )
+    }
+
+    fn from_parts(
+        setup_polynomials: SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+        hints: Vec<(usize, TranspilationVariant)>,
+        key_monomial_form: Crs<E, CrsForMonomialForm>,
+        key_lagrange_form: Option<Crs<E, CrsForLagrangeForm>>,
+    ) -> Result<Self, anyhow::Error> {
         let size = setup_polynomials.n.next_power_of_two().trailing_zeros();
         log::info!("circuit setup_polynomials.n {:?} size {}", setup_polynomials.n, size);
         let setup_power_of_two = std::cmp::max(size, SETUP_MIN_POW2);
         anyhow::ensure!(
             (SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&setup_power_of_two),
-            "setup power of two is not in the correct range"
+            invalid_input!("setup power of two is not in the correct range")
         );
 
         Ok(SetupForProver {
@@ -118,6 +431,21 @@ impl SetupForProver {
         })
     }
 
+    // save the circuit specific part of the setup, to be loaded by `prepare_setup_for_prover_from_proving_key`
+    pub fn write_proving_key<W: Write>(&self, circuit_digest: &[u8; 32], writer: W) -> std::io::Result<()> {
+        write_proving_key(circuit_digest, &self.setup_polynomials, &self.hints, writer)
+    }
+
+    // the power of two of the monomial form SRS
+    pub fn srs_power(&self) -> u32 {
+        self.key_monomial_form.g1_bases.len().trailing_zeros()
+    }
+
+    // the number of gates of the transpiled circuit
+    pub fn num_gates(&self) -> usize {
+        self.setup_polynomials.n
+    }
+
     // generate a verification key for a circuit
     pub fn make_verification_key(&self) -> Result<VerificationKey<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
         make_verification_key(&self.setup_polynomials, &self.key_monomial_form)
@@ -132,47 +460,31 @@ impl SetupForProver {
     pub fn prove<C: Circuit<E> + Clone>(
         &self,
         circuit: C,
-        transcript: &str,
-    ) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, SynthesisError> {
-        is_satisfied_using_one_shot_check(circuit.clone(), &self.hints).expect("must satisfy");
-        match &self.key_lagrange_form {
-            Some(key_lagrange_form) => match transcript {
-                "keccak" => prove::<_, _, RollingKeccakTranscript<<E as ScalarEngine>::Fr>>(
-                    circuit,
-                    &self.hints,
-                    &self.setup_polynomials,
-                    &self.key_monomial_form,
-                    key_lagrange_form,
-                ),
-                _ => {
-                    unimplemented!();
-                }
-            },
-            None => match transcript {
-                "keccak" => prove_by_steps::<_, _, RollingKeccakTranscript<<E as ScalarEngine>::Fr>>(
-                    circuit,
-                    &self.hints,
-                    &self.setup_polynomials,
-                    None,
-                    &self.key_monomial_form,
-                    None,
-                ),
-                "rescue" => {
-                    let (bn256_param, rns_param) = get_default_rescue_transcript_params();
-                    prove_by_steps::<_, _, RescueTranscriptForRNS<E>>(
-                        circuit,
-                        &self.hints,
-                        &self.setup_polynomials,
-                        None,
-                        &self.key_monomial_form,
-                        Some((&bn256_param, &rns_param)),
-                    )
-                }
-                _ => {
-                    unimplemented!("invalid transcript. use 'keccak' or 'rescue'");
-                }
-            },
-        }
+        transcript: Transcript,
+    ) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, anyhow::Error> {
+        is_satisfied_using_one_shot_check(circuit.clone(), &self.hints)
+            .map_err(|e| invalid_input!("witness does not satisfy the circuit: {:?}. run check-witness for details", e))?;
+        let proof = match (&self.key_lagrange_form, transcript) {
+            (Some(key_lagrange_form), Transcript::Keccak) => prove::<_, _, RollingKeccakTranscript<<E as ScalarEngine>::Fr>>(
+                circuit,
+                &self.hints,
+                &self.setup_polynomials,
+                &self.key_monomial_form,
+                key_lagrange_form,
+            )?,
+            (None, Transcript::Keccak) => prove_by_steps::<_, _, RollingKeccakTranscript<<E as ScalarEngine>::Fr>>(
+                circuit,
+                &self.hints,
+                &self.setup_polynomials,
+                None,
+                &self.key_monomial_form,
+                None,
+            )?,
+            // the lagrange form prover can't be given transcript params
+            (Some(_), Transcript::Rescue) => anyhow::bail!(invalid_input!("the rescue transcript can't be used with a lagrange form SRS")),
+            (None, Transcript::Rescue) => E::prove_with_rescue(circuit, &self.hints, &self.setup_polynomials, &self.key_monomial_form)?,
+        };
+        Ok(proof)
     }
 
     // calculate the lagrange_form SRS from a monomial_form SRS
@@ -186,35 +498,158 @@ impl SetupForProver {
 }
 
 // verify a plonk proof using a verification key
-pub fn verify(
+pub fn verify<E: PlonkEngine>(
     vk: &VerificationKey<E, PlonkCsWidth4WithNextStepParams>,
     proof: &Proof<E, PlonkCsWidth4WithNextStepParams>,
-    transcript: &str,
-) -> Result<bool, SynthesisError> {
+    transcript: Transcript,
+) -> Result<bool, anyhow::Error> {
     match transcript {
-        "keccak" => {
-            crate::bellman_ce::plonk::better_cs::verifier::verify::<_, _, RollingKeccakTranscript<<E as ScalarEngine>::Fr>>(proof, vk, None)
-        }
-        "rescue" => {
-            let (bn256_param, rns_param) = get_default_rescue_transcript_params();
-            crate::bellman_ce::plonk::better_cs::verifier::verify::<_, _, RescueTranscriptForRNS<E>>(
-                proof,
-                vk,
-                Some((&bn256_param, &rns_param)),
-            )
+        Transcript::Keccak => Ok(verifier::verify::<_, _, RollingKeccakTranscript<<E as ScalarEngine>::Fr>>(
+            proof, vk, None,
+        )?),
+        Transcript::Rescue => E::verify_with_rescue(vk, proof),
+    }
+}
+
+// check everything but the final pairing, which is returned as the two G1 points to pair with the vk's G2 elements
+fn verify_and_aggregate<E: PlonkEngine>(
+    vk: &VerificationKey<E, PlonkCsWidth4WithNextStepParams>,
+    proof: &Proof<E, PlonkCsWidth4WithNextStepParams>,
+    transcript: Transcript,
+) -> Result<(bool, [<E as Engine>::G1Affine; 2]), anyhow::Error> {
+    match transcript {
+        Transcript::Keccak => Ok(verifier::verify_and_aggregate::<
+            _,
+            _,
+            RollingKeccakTranscript<<E as ScalarEngine>::Fr>,
+        >(proof, vk, None)?),
+        Transcript::Rescue => E::verify_and_aggregate_with_rescue(vk, proof),
+    }
+}
+
+// verify many proofs of one circuit with a single multi-pairing, combining their pairing equations with random coefficients.
+// returns the indices of the invalid proofs, which are found by verifying one by one if the batch fails
+pub fn verify_batch<E: PlonkEngine>(
+    vk: &VerificationKey<E, PlonkCsWidth4WithNextStepParams>,
+    proofs: &[Proof<E, PlonkCsWidth4WithNextStepParams>],
+    transcript: Transcript,
+) -> Result<Vec<usize>, anyhow::Error> {
+    let mut rng = rand::thread_rng();
+    let mut invalid = Vec::new();
+    let mut pair_with_generator = <E as Engine>::G1::zero();
+    let mut pair_with_x = <E as Engine>::G1::zero();
+    for (i, proof) in proofs.iter().enumerate() {
+        let (valid, [generator_part, x_part]) = verify_and_aggregate(vk, proof, transcript)?;
+        if !valid {
+            invalid.push(i);
+            continue;
         }
-        _ => {
-            unimplemented!("invalid transcript. use 'keccak' or 'rescue'");
+        let r: <E as ScalarEngine>::Fr = rng.gen();
+        pair_with_generator.add_assign(&generator_part.mul(r.into_repr()));
+        pair_with_x.add_assign(&x_part.mul(r.into_repr()));
+    }
+
+    let batch_valid = E::final_exponentiation(&E::miller_loop(&[
+        (&pair_with_generator.into_affine().prepare(), &vk.g2_elements[0].prepare()),
+        (&pair_with_x.into_affine().prepare(), &vk.g2_elements[1].prepare()),
+    ]))
+    .map_or(false, |r| r == <E as Engine>::Fqk::one());
+    if batch_valid {
+        return Ok(invalid);
+    }
+
+    log::info!("batch verification failed, verifying proofs one by one");
+    for (i, proof) in proofs.iter().enumerate() {
+        if !invalid.contains(&i) && !verify(vk, proof, transcript)? {
+            invalid.push(i);
         }
     }
+    invalid.sort_unstable();
+    Ok(invalid)
+}
+
+// an engine the plonk prover runs on. the rescue transcript is defined over the rns of bn256, so it is only available there
+#[allow(clippy::ptr_arg)]
+pub trait PlonkEngine: CircomEngine {
+    fn prove_with_rescue<C: Circuit<Self> + Clone>(
+        circuit: C,
+        hints: &Vec<(usize, TranspilationVariant)>,
+        setup_polynomials: &SetupPolynomials<Self, PlonkCsWidth4WithNextStepParams>,
+        key_monomial_form: &Crs<Self, CrsForMonomialForm>,
+    ) -> Result<Proof<Self, PlonkCsWidth4WithNextStepParams>, anyhow::Error> {
+        let _ = (circuit, hints, setup_polynomials, key_monomial_form);
+        anyhow::bail!(invalid_input!("the rescue transcript is only supported on bn256"))
+    }
+
+    fn verify_with_rescue(
+        vk: &VerificationKey<Self, PlonkCsWidth4WithNextStepParams>,
+        proof: &Proof<Self, PlonkCsWidth4WithNextStepParams>,
+    ) -> Result<bool, anyhow::Error> {
+        let _ = (vk, proof);
+        anyhow::bail!(invalid_input!("the rescue transcript is only supported on bn256"))
+    }
+
+    fn verify_and_aggregate_with_rescue(
+        vk: &VerificationKey<Self, PlonkCsWidth4WithNextStepParams>,
+        proof: &Proof<Self, PlonkCsWidth4WithNextStepParams>,
+    ) -> Result<(bool, [<Self as Engine>::G1Affine; 2]), anyhow::Error> {
+        let _ = (vk, proof);
+        anyhow::bail!(invalid_input!("the rescue transcript is only supported on bn256"))
+    }
+}
+
+impl PlonkEngine for Bn256 {
+    fn prove_with_rescue<C: Circuit<Self> + Clone>(
+        circuit: C,
+        hints: &Vec<(usize, TranspilationVariant)>,
+        setup_polynomials: &SetupPolynomials<Self, PlonkCsWidth4WithNextStepParams>,
+        key_monomial_form: &Crs<Self, CrsForMonomialForm>,
+    ) -> Result<Proof<Self, PlonkCsWidth4WithNextStepParams>, anyhow::Error> {
+        let (bn256_param, rns_param) = get_default_rescue_transcript_params();
+        Ok(prove_by_steps::<_, _, RescueTranscriptForRNS<Bn256>>(
+            circuit,
+            hints,
+            setup_polynomials,
+            None,
+            key_monomial_form,
+            Some((&bn256_param, &rns_param)),
+        )?)
+    }
+
+    fn verify_with_rescue(
+        vk: &VerificationKey<Self, PlonkCsWidth4WithNextStepParams>,
+        proof: &Proof<Self, PlonkCsWidth4WithNextStepParams>,
+    ) -> Result<bool, anyhow::Error> {
+        let (bn256_param, rns_param) = get_default_rescue_transcript_params();
+        Ok(verifier::verify::<_, _, RescueTranscriptForRNS<Bn256>>(
+            proof,
+            vk,
+            Some((&bn256_param, &rns_param)),
+        )?)
+    }
+
+    fn verify_and_aggregate_with_rescue(
+        vk: &VerificationKey<Self, PlonkCsWidth4WithNextStepParams>,
+        proof: &Proof<Self, PlonkCsWidth4WithNextStepParams>,
+    ) -> Result<(bool, [<Self as Engine>::G1Affine; 2]), anyhow::Error> {
+        let (bn256_param, rns_param) = get_default_rescue_transcript_params();
+        Ok(verifier::verify_and_aggregate::<_, _, RescueTranscriptForRNS<Bn256>>(
+            proof,
+            vk,
+            Some((&bn256_param, &rns_param)),
+        )?)
+    }
 }
 
-fn get_default_rescue_transcript_params() -> (<E as RescueEngine>::Params, RnsParameters<E, <E as Engine>::Fq>) {
+impl PlonkEngine for Bls12 {}
+
+type RescueTranscriptParams = (<Bn256 as RescueEngine>::Params, RnsParameters<Bn256, <Bn256 as Engine>::Fq>);
+
+fn get_default_rescue_transcript_params() -> RescueTranscriptParams {
     use franklin_crypto::rescue::bn256::Bn256RescueParams;
-    let rns_params = RnsParameters::<E, <E as Engine>::Fq>::new_for_field(68, 110, 4);
+    let rns_params = RnsParameters::<Bn256, <Bn256 as Engine>::Fq>::new_for_field(68, 110, 4);
     let rescue_params = Bn256RescueParams::new_checked_2_into_1();
-    let transcript_params: (<E as RescueEngine>::Params, RnsParameters<E, <E as Engine>::Fq>) = (rescue_params, rns_params);
-    transcript_params
+    (rescue_params, rns_params)
 }
 
 #[cfg(test)]
@@ -223,6 +658,95 @@ mod tests {
 
     #[test]
     fn test_gen_key_monomial_form() {
-        gen_key_monomial_form(10).unwrap();
+        gen_key_monomial_form::<Bn256>(10).unwrap();
+        gen_key_monomial_form::<Bls12>(10).unwrap();
+    }
+
+    #[test]
+    fn test_transcript_from_str() {
+        for transcript in &[Transcript::Keccak, Transcript::Rescue] {
+            assert_eq!(transcript.to_string().parse::<Transcript>().unwrap(), *transcript);
+        }
+        assert!("poseidon".parse::<Transcript>().is_err());
+    }
+
+    #[test]
+    fn test_component_stats() {
+        let stat = |name: &str, num_gates| ConstraintStat {
+            name: name.to_string(),
+            num_gates,
+        };
+        let constraint_stats = vec![
+            stat("main.hasher.mix.out", 3),
+            stat("main.hasher.out", 2),
+            stat("main.out", 1),
+            stat("main.hasher.mix.t", 4),
+        ];
+        let component = |path: &str, num_constraints, num_gates| ComponentStat {
+            path: path.to_string(),
+            num_constraints,
+            num_gates,
+        };
+        assert_eq!(
+            component_stats(&constraint_stats),
+            vec![
+                component("main", 4, 10),
+                component("main.hasher", 3, 9),
+                component("main.hasher.mix", 2, 7)
+            ]
+        );
+        assert_eq!(folded_stacks(&constraint_stats), "main 1\nmain;hasher 2\nmain;hasher;mix 7\n");
+    }
+
+    #[test]
+    fn test_artifact_meta() {
+        let meta = ArtifactMeta {
+            curve: Curve::Bls12_381,
+            transcript: Some(Transcript::Rescue),
+            circuit_digest: [7u8; 32],
+            srs_power: 20,
+        };
+        let mut buf = vec![];
+        meta.write(&mut buf).unwrap();
+        assert_eq!(ArtifactMeta::read(&buf[..]).unwrap(), meta);
+
+        let mut unsupported = buf.clone();
+        unsupported[4] = 2;
+        assert!(ArtifactMeta::read(&unsupported[..]).is_err());
+
+        // a proof is not a meta
+        assert!(ArtifactMeta::read(&1u64.to_be_bytes()[..]).is_err());
+    }
+
+    #[test]
+    fn test_check_artifacts() {
+        let vk_meta = ArtifactMeta {
+            curve: Curve::Bn256,
+            transcript: None,
+            circuit_digest: [1u8; 32],
+            srs_power: 20,
+        };
+        let proof_meta = ArtifactMeta {
+            transcript: Some(Transcript::Rescue),
+            ..vk_meta
+        };
+        assert_eq!(check_artifacts(None, None, None).unwrap(), Transcript::Keccak);
+        assert_eq!(
+            check_artifacts(None, Some(&vk_meta), Some(&proof_meta)).unwrap(),
+            Transcript::Rescue
+        );
+        assert!(check_artifacts(Some(Transcript::Keccak), Some(&vk_meta), Some(&proof_meta)).is_err());
+
+        let other_circuit = ArtifactMeta {
+            circuit_digest: [2u8; 32],
+            ..proof_meta
+        };
+        assert!(check_artifacts(None, Some(&vk_meta), Some(&other_circuit)).is_err());
+
+        let other_curve = ArtifactMeta {
+            curve: Curve::Bls12_381,
+            ..proof_meta
+        };
+        assert!(check_artifacts(None, Some(&vk_meta), Some(&other_curve)).is_err());
     }
 }
@@ -0,0 +1,226 @@
+// parse the Powers of Tau files (`.ptau`) produced by snarkjs / the Hermez ceremony into a monomial form SRS
+use crate::bellman_ce::{
+    kate_commitment::{Crs, CrsForMonomialForm},
+    pairing::{
+        bn256::{Bn256, Fq, Fq2, G1Affine, G2Affine},
+        ff::{Field, PrimeField, PrimeFieldRepr},
+        CurveAffine, Engine,
+    },
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+};
+
+// PtauFile's header
+#[derive(Debug, Default)]
+pub struct Header {
+    pub field_size: u32,
+    pub prime: Vec<u8>,
+    pub power: u32,
+}
+
+fn read_header<R: Read>(mut reader: R, size: u64) -> Result<Header> {
+    let field_size = reader.read_u32::<LittleEndian>()?;
+    let mut prime = vec![0u8; field_size as usize];
+    reader.read_exact(&mut prime)?;
+    // newer snarkjs versions append the ceremony power after the power
+    if size != 4 + field_size as u64 + 4 && size != 4 + field_size as u64 + 8 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid header section size"));
+    }
+    Ok(Header {
+        field_size,
+        prime,
+        power: reader.read_u32::<LittleEndian>()?,
+    })
+}
+
+// field elements are stored little endian in montgomery form
+fn read_fq<R: Read>(mut reader: R) -> Result<Fq> {
+    let mut repr = Fq::zero().into_raw_repr();
+    repr.read_le(&mut reader)?;
+    Fq::from_raw_repr(repr).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_g1<R: Read>(mut reader: R) -> Result<G1Affine> {
+    let x = read_fq(&mut reader)?;
+    let y = read_fq(&mut reader)?;
+    G1Affine::from_xy_checked(x, y).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_g2<R: Read>(mut reader: R) -> Result<G2Affine> {
+    let x = Fq2 {
+        c0: read_fq(&mut reader)?,
+        c1: read_fq(&mut reader)?,
+    };
+    let y = Fq2 {
+        c0: read_fq(&mut reader)?,
+        c1: read_fq(&mut reader)?,
+    };
+    G2Affine::from_xy_checked(x, y).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+// read a monomial form SRS of size 2^power. `power` defaults to the power of the file
+pub fn from_reader<R: Read + Seek>(mut reader: R, power: Option<u32>) -> Result<Crs<Bn256, CrsForMonomialForm>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != *b"ptau" {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid magic number"));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != 1 {
+        return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+    }
+
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+
+    // section type -> file offset
+    let mut section_offsets = HashMap::<u32, u64>::new();
+    let mut section_sizes = HashMap::<u32, u64>::new();
+
+    // get file offset of each section
+    for _ in 0..num_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let section_size = reader.read_u64::<LittleEndian>()?;
+        let offset = reader.seek(SeekFrom::Current(0))?;
+        section_offsets.insert(section_type, offset);
+        section_sizes.insert(section_type, section_size);
+        reader.seek(SeekFrom::Current(section_size as i64))?;
+    }
+
+    let header_type = 1;
+    let tau_g1_type = 2;
+    let tau_g2_type = 3;
+    for section_type in &[header_type, tau_g1_type, tau_g2_type] {
+        if !section_offsets.contains_key(section_type) {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Missing section {}", section_type)));
+        }
+    }
+
+    reader.seek(SeekFrom::Start(section_offsets[&header_type]))?;
+    let header = read_header(&mut reader, section_sizes[&header_type])?;
+    if header.field_size != 32 {
+        return Err(Error::new(ErrorKind::InvalidData, "This parser only supports 32-byte fields"));
+    }
+    if header.prime != hex!("47fd7cd8168c203c8dca7168916a81975d588181b64550b829a031e1724e6430") {
+        return Err(Error::new(ErrorKind::InvalidData, "This parser only supports bn256"));
+    }
+    let power = power.unwrap_or(header.power);
+    if power == 0 || power > header.power {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Power {} is not in the range of the file [1, {}]", power, header.power),
+        ));
+    }
+
+    // tauG1 holds 2^(power+1)-1 points, tauG2 holds 2^power points
+    if section_sizes[&tau_g1_type] != ((2u64 << header.power) - 1) * 64 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid tauG1 section size"));
+    }
+    if section_sizes[&tau_g2_type] != (1u64 << header.power) * 128 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid tauG2 section size"));
+    }
+
+    reader.seek(SeekFrom::Start(section_offsets[&tau_g1_type]))?;
+    let mut g1_bases = Vec::with_capacity(1 << power);
+    for _ in 0..(1u64 << power) {
+        g1_bases.push(read_g1(&mut reader)?);
+    }
+
+    reader.seek(SeekFrom::Start(section_offsets[&tau_g2_type]))?;
+    let g2_bases = vec![read_g2(&mut reader)?, read_g2(&mut reader)?];
+
+    if g1_bases[0] != G1Affine::one() {
+        return Err(Error::new(ErrorKind::InvalidData, "Powers of tau must start from the generator"));
+    }
+    // e(tau * g1, g2) == e(g1, tau * g2)
+    if Bn256::pairing(g1_bases[1], g2_bases[0]) != Bn256::pairing(g1_bases[0], g2_bases[1]) {
+        return Err(Error::new(ErrorKind::InvalidData, "tauG1 and tauG2 are inconsistent"));
+    }
+
+    Ok(Crs::<Bn256, CrsForMonomialForm>::new(g1_bases, g2_bases))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::bellman_ce::pairing::{bn256::Fr, CurveProjective};
+    use byteorder::WriteBytesExt;
+
+    fn write_fq(buf: &mut Vec<u8>, fq: Fq) {
+        fq.into_raw_repr().write_le(buf).unwrap();
+    }
+
+    fn write_section(buf: &mut Vec<u8>, section_type: u32, data: &[u8]) {
+        buf.write_u32::<LittleEndian>(section_type).unwrap();
+        buf.write_u64::<LittleEndian>(data.len() as u64).unwrap();
+        buf.extend_from_slice(data);
+    }
+
+    // a ptau file of power 2 with tau = 42
+    fn sample() -> (Vec<u8>, Vec<G1Affine>, Vec<G2Affine>) {
+        let power = 2;
+        let tau = Fr::from_str("42").unwrap();
+        let mut tau_powers = vec![Fr::one()];
+        for i in 1..(2usize << power) - 1 {
+            let mut next = tau_powers[i - 1];
+            next.mul_assign(&tau);
+            tau_powers.push(next);
+        }
+        let g1_points: Vec<G1Affine> = tau_powers
+            .iter()
+            .map(|t| G1Affine::one().mul(t.into_repr()).into_affine())
+            .collect();
+        let g2_points: Vec<G2Affine> = tau_powers[..1 << power]
+            .iter()
+            .map(|t| G2Affine::one().mul(t.into_repr()).into_affine())
+            .collect();
+
+        let mut header = vec![];
+        header.write_u32::<LittleEndian>(32).unwrap();
+        header.extend_from_slice(&hex!("47fd7cd8168c203c8dca7168916a81975d588181b64550b829a031e1724e6430"));
+        header.write_u32::<LittleEndian>(power).unwrap();
+        header.write_u32::<LittleEndian>(power).unwrap();
+
+        let mut tau_g1 = vec![];
+        for p in &g1_points {
+            let (x, y) = p.into_xy_unchecked();
+            write_fq(&mut tau_g1, x);
+            write_fq(&mut tau_g1, y);
+        }
+        let mut tau_g2 = vec![];
+        for p in &g2_points {
+            let (x, y) = p.into_xy_unchecked();
+            write_fq(&mut tau_g2, x.c0);
+            write_fq(&mut tau_g2, x.c1);
+            write_fq(&mut tau_g2, y.c0);
+            write_fq(&mut tau_g2, y.c1);
+        }
+
+        let mut buf = b"ptau".to_vec();
+        buf.write_u32::<LittleEndian>(1).unwrap();
+        buf.write_u32::<LittleEndian>(3).unwrap();
+        write_section(&mut buf, 1, &header);
+        write_section(&mut buf, 2, &tau_g1);
+        write_section(&mut buf, 3, &tau_g2);
+        (buf, g1_points, g2_points)
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let (data, g1_points, g2_points) = sample();
+
+        let crs = from_reader(Cursor::new(&data[..]), None).unwrap();
+        assert_eq!(crs.g1_bases.as_ref(), &g1_points[..4]);
+        assert_eq!(crs.g2_monomial_bases.as_ref(), &g2_points[..2]);
+
+        let crs = from_reader(Cursor::new(&data[..]), Some(1)).unwrap();
+        assert_eq!(crs.g1_bases.as_ref(), &g1_points[..2]);
+
+        assert!(from_reader(Cursor::new(&data[..]), Some(3)).is_err());
+    }
+}
@@ -1,17 +1,33 @@
 // some codes borrowed from https://github.com/poma/zkutil/blob/master/src/r1cs_reader.rs
 #![allow(unused_variables, dead_code)]
 use crate::bellman_ce::pairing::{
-    bn256::Bn256,
     ff::{Field, PrimeField, PrimeFieldRepr},
     Engine,
 };
-use crate::circom_circuit::Constraint;
+use crate::circom_circuit::{Constraint, CustomGate, CustomGateApplication};
+use crate::curve::{CircomEngine, Curve};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::{
     collections::HashMap,
-    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
 };
 
+// the highest r1cs file version known to this parser
+const MAX_VERSION: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("bad magic number")]
+    BadMagic,
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u32),
+    // the prime is little endian, as it is written in the file
+    #[error("the circuit is not compiled for the {curve} curve")]
+    WrongPrime { prime: Vec<u8>, curve: Curve },
+}
+
 // R1CSFile's header
 #[derive(Debug, Default)]
 pub struct Header {
@@ -32,21 +48,23 @@ pub struct R1CSFile<E: Engine> {
     pub header: Header,
     pub constraints: Vec<Constraint<E>>,
     pub wire_mapping: Vec<u64>,
+    pub custom_gates: Vec<CustomGate<E>>,
+    pub custom_gates_applied: Vec<CustomGateApplication>,
 }
 
-fn read_field<R: Read, E: Engine>(mut reader: R) -> Result<E::Fr> {
+fn read_field<R: Read, E: Engine>(mut reader: R) -> io::Result<E::Fr> {
     let mut repr = E::Fr::zero().into_repr();
     repr.read_le(&mut reader)?;
-    let fr = E::Fr::from_repr(repr).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let fr = E::Fr::from_repr(repr).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
     Ok(fr)
 }
 
-fn read_header<R: Read>(mut reader: R, size: u64) -> Result<Header> {
+fn read_header<R: Read>(mut reader: R, size: u64) -> io::Result<Header> {
     let field_size = reader.read_u32::<LittleEndian>()?;
     let mut prime_size = vec![0u8; field_size as usize];
     reader.read_exact(&mut prime_size)?;
     if size != 32 + field_size as u64 {
-        return Err(Error::new(ErrorKind::InvalidData, "Invalid header section size"));
+        return Err(io::Error::new(ErrorKind::InvalidData, "Invalid header section size"));
     }
 
     Ok(Header {
@@ -61,7 +79,7 @@ fn read_header<R: Read>(mut reader: R, size: u64) -> Result<Header> {
     })
 }
 
-fn read_constraint_vec<R: Read, E: Engine>(mut reader: R, header: &Header) -> Result<Vec<(usize, E::Fr)>> {
+fn read_constraint_vec<R: Read, E: Engine>(mut reader: R, header: &Header) -> io::Result<Vec<(usize, E::Fr)>> {
     let n_vec = reader.read_u32::<LittleEndian>()? as usize;
     let mut vec = Vec::with_capacity(n_vec);
     for _ in 0..n_vec {
@@ -70,7 +88,7 @@ fn read_constraint_vec<R: Read, E: Engine>(mut reader: R, header: &Header) -> Re
     Ok(vec)
 }
 
-fn read_constraints<R: Read, E: Engine>(mut reader: R, size: u64, header: &Header) -> Result<Vec<Constraint<E>>> {
+fn read_constraints<R: Read, E: Engine>(mut reader: R, size: u64, header: &Header) -> io::Result<Vec<Constraint<E>>> {
     // todo check section size
     let mut vec = Vec::with_capacity(header.n_constraints as usize);
     for _ in 0..header.n_constraints {
@@ -83,73 +101,166 @@ fn read_constraints<R: Read, E: Engine>(mut reader: R, size: u64, header: &Heade
     Ok(vec)
 }
 
-fn read_map<R: Read>(mut reader: R, size: u64, header: &Header) -> Result<Vec<u64>> {
+fn read_map<R: Read>(mut reader: R, size: u64, header: &Header) -> io::Result<Vec<u64>> {
     if size != header.n_wires as u64 * 8 {
-        return Err(Error::new(ErrorKind::InvalidData, "Invalid map section size"));
+        return Err(io::Error::new(ErrorKind::InvalidData, "Invalid map section size"));
     }
     let mut vec = Vec::with_capacity(header.n_wires as usize);
     for _ in 0..header.n_wires {
         vec.push(reader.read_u64::<LittleEndian>()?);
     }
     if vec[0] != 0 {
-        return Err(Error::new(ErrorKind::InvalidData, "Wire 0 should always be mapped to 0"));
+        return Err(io::Error::new(ErrorKind::InvalidData, "Wire 0 should always be mapped to 0"));
+    }
+    Ok(vec)
+}
+
+fn read_string<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut buf = Vec::new();
+    loop {
+        let byte = reader.read_u8()?;
+        if byte == 0 {
+            break;
+        }
+        buf.push(byte);
+    }
+    String::from_utf8(buf).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_custom_gates_list<R: Read, E: Engine>(mut reader: R, size: u64, header: &Header) -> io::Result<Vec<CustomGate<E>>> {
+    let num = reader.read_u32::<LittleEndian>()?;
+    let mut vec = Vec::with_capacity(num as usize);
+    for _ in 0..num {
+        let template_name = read_string(&mut reader)?;
+        let num_parameters = reader.read_u32::<LittleEndian>()?;
+        let mut parameters = Vec::with_capacity(num_parameters as usize);
+        for _ in 0..num_parameters {
+            parameters.push(read_field::<&mut R, E>(&mut reader)?);
+        }
+        vec.push(CustomGate { template_name, parameters });
+    }
+    Ok(vec)
+}
+
+fn read_custom_gates_applied<R: Read>(mut reader: R, size: u64, header: &Header) -> io::Result<Vec<CustomGateApplication>> {
+    let num = reader.read_u32::<LittleEndian>()?;
+    let mut vec = Vec::with_capacity(num as usize);
+    for _ in 0..num {
+        let gate_id = reader.read_u32::<LittleEndian>()? as usize;
+        let num_signals = reader.read_u32::<LittleEndian>()?;
+        let mut signals = Vec::with_capacity(num_signals as usize);
+        for _ in 0..num_signals {
+            let signal = reader.read_u64::<LittleEndian>()?;
+            if signal >= header.n_wires as u64 {
+                return Err(io::Error::new(ErrorKind::InvalidData, "Custom gate signal out of range"));
+            }
+            signals.push(signal as usize);
+        }
+        vec.push(CustomGateApplication { gate_id, signals });
     }
     Ok(vec)
 }
 
-pub fn from_reader<R: Read + Seek>(mut reader: R) -> Result<R1CSFile<Bn256>> {
+// section type -> (file offset, size)
+type Sections = HashMap<u32, (u64, u64)>;
+
+const HEADER_TYPE: u32 = 1;
+const CONSTRAINT_TYPE: u32 = 2;
+const WIRE2LABEL_TYPE: u32 = 3;
+const CUSTOM_GATES_LIST_TYPE: u32 = 4;
+const CUSTOM_GATES_APPLIED_TYPE: u32 = 5;
+
+// check the magic and version, and locate every section
+fn read_sections<R: Read + Seek>(mut reader: R) -> Result<(u32, Sections), Error> {
     let mut magic = [0u8; 4];
     reader.read_exact(&mut magic)?;
     if magic != [0x72, 0x31, 0x63, 0x73] {
         // magic = "r1cs"
-        return Err(Error::new(ErrorKind::InvalidData, "Invalid magic number"));
+        return Err(Error::BadMagic);
     }
 
     let version = reader.read_u32::<LittleEndian>()?;
-    if version != 1 {
-        return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+    if version == 0 || version > MAX_VERSION {
+        return Err(Error::UnsupportedVersion(version));
     }
 
     let num_sections = reader.read_u32::<LittleEndian>()?;
 
-    // section type -> file offset
-    let mut section_offsets = HashMap::<u32, u64>::new();
-    let mut section_sizes = HashMap::<u32, u64>::new();
-
-    // get file offset of each section
+    let mut sections = Sections::new();
     for _ in 0..num_sections {
         let section_type = reader.read_u32::<LittleEndian>()?;
         let section_size = reader.read_u64::<LittleEndian>()?;
         let offset = reader.seek(SeekFrom::Current(0))?;
-        section_offsets.insert(section_type, offset);
-        section_sizes.insert(section_type, section_size);
+        sections.insert(section_type, (offset, section_size));
         reader.seek(SeekFrom::Current(section_size as i64))?;
     }
+    Ok((version, sections))
+}
 
-    let header_type = 1;
-    let constraint_type = 2;
-    let wire2label_type = 3;
+fn seek_section<R: Seek>(mut reader: R, sections: &Sections, section_type: u32) -> io::Result<u64> {
+    let (offset, size) = sections
+        .get(&section_type)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, format!("Missing section {}", section_type)))?;
+    reader.seek(SeekFrom::Start(*offset))?;
+    Ok(*size)
+}
 
-    reader.seek(SeekFrom::Start(*section_offsets.get(&header_type).unwrap()))?;
-    let header = read_header(&mut reader, *section_sizes.get(&header_type).unwrap())?;
+fn read_header_section<R: Read + Seek>(mut reader: R, sections: &Sections) -> io::Result<Header> {
+    let size = seek_section(&mut reader, sections, HEADER_TYPE)?;
+    let header = read_header(&mut reader, size)?;
     if header.field_size != 32 {
-        return Err(Error::new(ErrorKind::InvalidData, "This parser only supports 32-byte fields"));
+        return Err(io::Error::new(ErrorKind::InvalidData, "This parser only supports 32-byte fields"));
     }
-    if header.prime_size != hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430") {
-        return Err(Error::new(ErrorKind::InvalidData, "This parser only supports bn256"));
+    Ok(header)
+}
+
+// read which curve a circuit is compiled for, without parsing its constraints
+pub fn read_curve<R: Read + Seek>(mut reader: R) -> Result<Curve, Error> {
+    let (_, sections) = read_sections(&mut reader)?;
+    let header = read_header_section(&mut reader, &sections)?;
+    Curve::from_prime(&header.prime_size).ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Unsupported field prime").into())
+}
+
+pub fn from_reader<E: CircomEngine, R: Read + Seek>(mut reader: R) -> Result<R1CSFile<E>, Error> {
+    let (version, sections) = read_sections(&mut reader)?;
+    let header = read_header_section(&mut reader, &sections)?;
+    if header.prime_size != E::PRIME {
+        return Err(Error::WrongPrime {
+            prime: header.prime_size,
+            curve: E::CURVE,
+        });
     }
 
-    reader.seek(SeekFrom::Start(*section_offsets.get(&constraint_type).unwrap()))?;
-    let constraints = read_constraints::<&mut R, Bn256>(&mut reader, *section_sizes.get(&constraint_type).unwrap(), &header)?;
+    let size = seek_section(&mut reader, &sections, CONSTRAINT_TYPE)?;
+    let constraints = read_constraints::<&mut R, E>(&mut reader, size, &header)?;
 
-    reader.seek(SeekFrom::Start(*section_offsets.get(&wire2label_type).unwrap()))?;
-    let wire_mapping = read_map(&mut reader, *section_sizes.get(&wire2label_type).unwrap(), &header)?;
+    let size = seek_section(&mut reader, &sections, WIRE2LABEL_TYPE)?;
+    let wire_mapping = read_map(&mut reader, size, &header)?;
+
+    // the custom gates sections only exist when the circuit is compiled with `pragma custom_templates`
+    let custom_gates = if sections.contains_key(&CUSTOM_GATES_LIST_TYPE) {
+        let size = seek_section(&mut reader, &sections, CUSTOM_GATES_LIST_TYPE)?;
+        read_custom_gates_list::<&mut R, E>(&mut reader, size, &header)?
+    } else {
+        Vec::new()
+    };
+    let custom_gates_applied = if sections.contains_key(&CUSTOM_GATES_APPLIED_TYPE) {
+        let size = seek_section(&mut reader, &sections, CUSTOM_GATES_APPLIED_TYPE)?;
+        read_custom_gates_applied(&mut reader, size, &header)?
+    } else {
+        Vec::new()
+    };
+    if custom_gates_applied.iter().any(|a| a.gate_id >= custom_gates.len()) {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Custom gate application refers to an unknown gate").into());
+    }
 
     Ok(R1CSFile {
         version,
         header,
         constraints,
         wire_mapping,
+        custom_gates,
+        custom_gates_applied,
     })
 }
 
@@ -158,6 +269,7 @@ mod tests {
     use std::io::{BufReader, Cursor};
 
     use super::*;
+    use crate::bellman_ce::pairing::{bls12_381::Bls12, bn256::Bn256};
 
     #[test]
     fn sample() {
@@ -215,7 +327,7 @@ mod tests {
 
         use crate::bellman_ce::pairing::ff;
         let reader = BufReader::new(Cursor::new(&data[..]));
-        let file = from_reader(reader).unwrap();
+        let file = from_reader::<Bn256, _>(reader).unwrap();
         assert_eq!(file.version, 1);
 
         assert_eq!(file.header.field_size, 32);
@@ -240,11 +352,64 @@ mod tests {
 
         assert_eq!(file.wire_mapping.len(), 7);
         assert_eq!(file.wire_mapping[1], 3);
+
+        assert_eq!(read_curve(Cursor::new(&data[..])).unwrap(), Curve::Bn256);
+        assert!(from_reader::<Bls12, _>(Cursor::new(&data[..])).is_err());
+    }
+
+    #[test]
+    fn sample_with_custom_gates() {
+        let data = hex!(
+            "
+        72316373
+        01000000
+        05000000
+        01000000 40000000 00000000
+        20000000
+        010000f0 93f5e143 9170b979 48e83328 5d588181 b64550b8 29a031e1 724e6430
+        03000000
+        01000000
+        01000000
+        00000000
+        03000000 00000000
+        00000000
+        02000000 00000000 00000000
+        03000000 18000000 00000000
+        00000000 00000000
+        01000000 00000000
+        02000000 00000000
+        04000000 2d000000 00000000
+        01000000
+        434d756c 00
+        01000000
+        03000000 00000000 00000000 00000000 00000000 00000000 00000000 00000000
+        05000000 1c000000 00000000
+        01000000
+        00000000
+        02000000
+        01000000 00000000
+        02000000 00000000
+    "
+        );
+
+        use crate::bellman_ce::pairing::ff;
+        let reader = BufReader::new(Cursor::new(&data[..]));
+        let file = from_reader::<Bn256, _>(reader).unwrap();
+        assert_eq!(file.header.n_wires, 3);
+        assert_eq!(file.constraints.len(), 0);
+
+        assert_eq!(file.custom_gates.len(), 1);
+        assert_eq!(file.custom_gates[0].template_name, "CMul");
+        assert_eq!(file.custom_gates[0].parameters, vec![ff::from_hex("0x03").unwrap()]);
+
+        assert_eq!(file.custom_gates_applied.len(), 1);
+        assert_eq!(file.custom_gates_applied[0].gate_id, 0);
+        assert_eq!(file.custom_gates_applied[0].signals, vec![1, 2]);
     }
 
     #[test]
     fn test_reader_size_fail() {
-        // fn read_header<R: Read>(mut reader: R, size: u64) -> Result<Header>
+        // fn read_header<R: Read>(mut reader: R, size: u64) -> io::Result<Header>
         let mut buf: Vec<u8> = 32_u32.to_le_bytes().to_vec();
         buf.resize(4 + 32, 0);
         let err = read_header(&mut buf.as_slice(), 32).err().unwrap();
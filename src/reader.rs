@@ -1,9 +1,10 @@
-use anyhow::{bail, format_err};
 use byteorder::{LittleEndian, ReadBytesExt};
 use itertools::Itertools;
 use std::collections::BTreeMap;
-use std::fs::{File, OpenOptions};
+use std::fmt::Display;
+use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::Path;
 use std::str;
 
 use crate::bellman_ce::{
@@ -17,79 +18,328 @@ use crate::bellman_ce::{
 };
 
 use crate::circom_circuit::{CircuitJson, R1CS};
-use crate::recursive::{AggregatedProof, RecursiveVerificationKey};
+use crate::curve::{CircomEngine, Curve};
+use crate::groth16;
+use crate::plonk::{self, ArtifactMeta, ProvingKey};
+use crate::r1cs_file;
+use crate::recursive::{AggregatedProof, RecursiveMeta, RecursiveProvingKey, RecursiveVerificationKey};
+use crate::symbols::CircuitSymbols;
+use crate::{ArtifactMetaStr, Groth16ProofStr, ProofStr, VerificationKeyStr};
+
+/// errors of loading circuits, witnesses, keys and proofs
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to open {path}: {source}")]
+    Open { path: String, source: std::io::Error },
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid {0} file: bad magic number")]
+    BadMagic(&'static str),
+    #[error("unsupported {format} file version {version}")]
+    UnsupportedVersion { format: &'static str, version: u32 },
+    #[error("field prime 0x{prime} is not the prime of {curve}")]
+    WrongPrime { prime: String, curve: Curve },
+    #[error("invalid field element {0}")]
+    BadFieldElement(String),
+    #[error("{what} size mismatch: expected {expected}, got {actual}")]
+    SizeMismatch { what: &'static str, expected: u64, actual: u64 },
+    #[error("malformed {what}: {reason}")]
+    Malformed { what: &'static str, reason: String },
+    #[error("no proof found in {0}")]
+    EmptyProofList(String),
+    #[error("no verification key found in {0}")]
+    EmptyVerificationKeyList(String),
+    #[error("proof {path} has {actual} inputs, but {expected} are expected")]
+    NumInputsMismatch { path: String, expected: usize, actual: usize },
+}
+
+fn open(filename: &str) -> Result<File, Error> {
+    File::open(filename).map_err(|source| Error::Open {
+        path: filename.to_string(),
+        source,
+    })
+}
+
+// most artifact parsers report malformed data as io errors, keep which artifact it was about
+fn malformed<T: Display>(what: &'static str) -> impl FnOnce(T) -> Error {
+    move |e| Error::Malformed {
+        what,
+        reason: e.to_string(),
+    }
+}
+
+impl From<r1cs_file::Error> for Error {
+    fn from(e: r1cs_file::Error) -> Self {
+        match e {
+            r1cs_file::Error::BadMagic => Error::BadMagic("r1cs"),
+            r1cs_file::Error::UnsupportedVersion(version) => Error::UnsupportedVersion { format: "r1cs", version },
+            r1cs_file::Error::WrongPrime { prime, curve } => Error::WrongPrime {
+                prime: hex::encode(prime.iter().rev().copied().collect_vec()),
+                curve,
+            },
+            r1cs_file::Error::Io(e) => malformed("r1cs")(e),
+        }
+    }
+}
 
 /// load proof by filename
-pub fn load_proof<E: Engine>(filename: &str) -> Proof<E, PlonkCsWidth4WithNextStepParams> {
-    Proof::<E, PlonkCsWidth4WithNextStepParams>::read(File::open(filename).expect("read proof file err")).expect("read proof err")
+pub fn load_proof<E: Engine>(filename: &str) -> Result<Proof<E, PlonkCsWidth4WithNextStepParams>, Error> {
+    Ok(load_proof_with_meta::<E>(filename)?.0)
+}
+
+/// load proof by filename, together with the meta saved next to it if there is one
+pub fn load_proof_with_meta<E: Engine>(filename: &str) -> Result<(Proof<E, PlonkCsWidth4WithNextStepParams>, Option<ArtifactMeta>), Error> {
+    let reader = BufReader::new(open(filename)?);
+    let meta = load_artifact_meta(filename)?;
+    let proof = Proof::<E, PlonkCsWidth4WithNextStepParams>::read(reader).map_err(malformed("proof"))?;
+    Ok((proof, meta))
+}
+
+/// load proof json by filename, together with its meta if it has one
+pub fn load_proof_json_with_meta<E: Engine>(
+    filename: &str,
+) -> Result<(Proof<E, PlonkCsWidth4WithNextStepParams>, Option<ArtifactMeta>), Error> {
+    let proof_str: ProofStr = serde_json::from_reader(BufReader::new(open(filename)?)).map_err(malformed("proof json"))?;
+    let meta = proof_str
+        .meta
+        .as_ref()
+        .map(ArtifactMeta::try_from)
+        .transpose()
+        .map_err(malformed("proof json"))?;
+    let proof = Proof::try_from(proof_str).map_err(malformed("proof json"))?;
+    Ok((proof, meta))
 }
 
 /// load multiple proofs form a list
-pub fn load_proofs_from_list<E: Engine>(list: &str) -> Vec<Proof<E, PlonkCsWidth4WithNextStepParams>> {
-    let file = File::open(list).expect("read proof list file err");
-    let lines: Vec<String> = BufReader::new(file).lines().map(|l| l.expect("could not parse line")).collect();
-    let proofs: Vec<Proof<E, PlonkCsWidth4WithNextStepParams>> = lines
-        .iter()
-        .map(|l| {
-            log::info!("reading {:?}", l);
-            load_proof::<E>(l)
-        })
-        .collect();
+pub fn load_proofs_from_list<E: Engine>(list: &str) -> Result<Vec<Proof<E, PlonkCsWidth4WithNextStepParams>>, Error> {
+    Ok(load_proofs_with_meta_from_list::<E>(list)?
+        .into_iter()
+        .map(|(proof, _)| proof)
+        .collect())
+}
 
-    assert!(!proofs.is_empty(), "no proof file found!");
+/// load multiple proofs form a list, together with their meta headers
+#[allow(clippy::type_complexity)]
+pub fn load_proofs_with_meta_from_list<E: Engine>(
+    list: &str,
+) -> Result<Vec<(Proof<E, PlonkCsWidth4WithNextStepParams>, Option<ArtifactMeta>)>, Error> {
+    let lines = read_list(list)?;
+    let mut proofs: Vec<(Proof<E, PlonkCsWidth4WithNextStepParams>, Option<ArtifactMeta>)> = Vec::with_capacity(lines.len());
+    for l in &lines {
+        log::info!("reading {:?}", l);
+        let (proof, meta) = load_proof_with_meta::<E>(l)?;
+        if let Some(expected) = proofs.first().map(|(p, _)| p.num_inputs) {
+            if proof.num_inputs != expected {
+                return Err(Error::NumInputsMismatch {
+                    path: l.to_string(),
+                    expected,
+                    actual: proof.num_inputs,
+                });
+            }
+        }
+        proofs.push((proof, meta));
+    }
 
-    let num_inputs = proofs[0].num_inputs;
-    for p in &proofs {
-        assert_eq!(p.num_inputs, num_inputs, "proofs num_inputs mismatch!");
+    if proofs.is_empty() {
+        return Err(Error::EmptyProofList(list.to_string()));
     }
 
-    proofs
+    Ok(proofs)
+}
+
+/// load multiple verification keys from a list
+pub fn load_verification_keys_from_list<E: Engine>(list: &str) -> Result<Vec<VerificationKey<E, PlonkCsWidth4WithNextStepParams>>, Error> {
+    let mut vks = Vec::new();
+    for l in read_list(list)? {
+        log::info!("reading {:?}", l);
+        vks.push(load_verification_key::<E>(&l)?);
+    }
+    if vks.is_empty() {
+        return Err(Error::EmptyVerificationKeyList(list.to_string()));
+    }
+    Ok(vks)
+}
+
+// the file names in a list file, one per line
+fn read_list(list: &str) -> Result<Vec<String>, Error> {
+    let lines: Vec<String> = BufReader::new(open(list)?).lines().collect::<Result<_, _>>()?;
+    Ok(lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
 }
 
 /// load recursive proof file by filename
-pub fn load_aggregated_proof(filename: &str) -> AggregatedProof {
-    AggregatedProof::read(File::open(filename).expect("read aggregated proof file err")).expect("read aggregated proof err")
+pub fn load_aggregated_proof(filename: &str) -> Result<AggregatedProof, Error> {
+    Ok(load_aggregated_proof_with_meta(filename)?.0)
+}
+
+/// load recursive proof file by filename, together with its meta header if it has one
+pub fn load_aggregated_proof_with_meta(filename: &str) -> Result<(AggregatedProof, Option<RecursiveMeta>), Error> {
+    let mut reader = BufReader::new(open(filename)?);
+    let meta = RecursiveMeta::read_optional(&mut reader).map_err(malformed("aggregated proof"))?;
+    let proof = AggregatedProof::read(reader).map_err(malformed("aggregated proof"))?;
+    Ok((proof, meta))
 }
 
 /// load verification key file by filename
-pub fn load_verification_key<E: Engine>(filename: &str) -> VerificationKey<E, PlonkCsWidth4WithNextStepParams> {
-    let mut reader = BufReader::with_capacity(1 << 24, File::open(filename).expect("read vk file err"));
-    VerificationKey::<E, PlonkCsWidth4WithNextStepParams>::read(&mut reader).expect("read vk err")
+pub fn load_verification_key<E: Engine>(filename: &str) -> Result<VerificationKey<E, PlonkCsWidth4WithNextStepParams>, Error> {
+    Ok(load_verification_key_with_meta::<E>(filename)?.0)
+}
+
+/// load verification key file by filename, together with the meta saved next to it if there is one
+pub fn load_verification_key_with_meta<E: Engine>(
+    filename: &str,
+) -> Result<(VerificationKey<E, PlonkCsWidth4WithNextStepParams>, Option<ArtifactMeta>), Error> {
+    let mut reader = BufReader::with_capacity(1 << 24, open(filename)?);
+    let meta = load_artifact_meta(filename)?;
+    let vk = VerificationKey::<E, PlonkCsWidth4WithNextStepParams>::read(&mut reader).map_err(malformed("verification key"))?;
+    Ok((vk, meta))
+}
+
+/// load verification key json by filename, together with its meta if it has one
+pub fn load_verification_key_json_with_meta<E: Engine>(
+    filename: &str,
+) -> Result<(VerificationKey<E, PlonkCsWidth4WithNextStepParams>, Option<ArtifactMeta>), Error> {
+    let vk_str: VerificationKeyStr =
+        serde_json::from_reader(BufReader::new(open(filename)?)).map_err(malformed("verification key json"))?;
+    let meta = vk_str
+        .meta
+        .as_ref()
+        .map(ArtifactMeta::try_from)
+        .transpose()
+        .map_err(malformed("verification key json"))?;
+    let vk = VerificationKey::try_from(vk_str).map_err(malformed("verification key json"))?;
+    Ok((vk, meta))
+}
+
+/// load only the meta of a proof or verification key BIN or JSON file, to tell its curve before loading it
+pub fn load_artifact_meta(filename: &str) -> Result<Option<ArtifactMeta>, Error> {
+    if !filename.ends_with(".json") {
+        let meta_file = plonk::artifact_meta_file(filename);
+        if !Path::new(&meta_file).exists() {
+            return Ok(None);
+        }
+        let meta = ArtifactMeta::read(BufReader::new(open(&meta_file)?)).map_err(malformed("artifact meta"))?;
+        return Ok(Some(meta));
+    }
+    let reader = BufReader::new(open(filename)?);
+    #[derive(serde::Deserialize)]
+    struct Artifact {
+        #[serde(default)]
+        meta: Option<ArtifactMetaStr>,
+    }
+    let artifact: Artifact = serde_json::from_reader(reader).map_err(malformed("artifact json"))?;
+    artifact
+        .meta
+        .as_ref()
+        .map(ArtifactMeta::try_from)
+        .transpose()
+        .map_err(malformed("artifact json"))
+}
+
+/// load proving key file by filename
+pub fn load_proving_key<E: Engine>(filename: &str) -> Result<ProvingKey<E>, Error> {
+    let mut reader = BufReader::with_capacity(1 << 24, open(filename)?);
+    ProvingKey::<E>::read(&mut reader).map_err(malformed("proving key"))
+}
+
+/// load groth16 proving parameters by filename
+pub fn load_groth16_parameters<E: CircomEngine>(filename: &str) -> Result<groth16::ProvingParameters<E>, Error> {
+    let mut reader = BufReader::with_capacity(1 << 24, open(filename)?);
+    groth16::ProvingParameters::read(&mut reader).map_err(malformed("groth16 parameters"))
+}
+
+/// load the curve groth16 proving parameters are generated for by filename
+pub fn load_groth16_parameters_curve(filename: &str) -> Result<Curve, Error> {
+    groth16::read_curve(open(filename)?).map_err(malformed("groth16 parameters"))
+}
+
+/// load groth16 verification key by filename
+pub fn load_groth16_verification_key<E: Engine>(filename: &str) -> Result<groth16::VerifyingKey<E>, Error> {
+    let reader = BufReader::new(open(filename)?);
+    groth16::VerifyingKey::read(reader).map_err(malformed("groth16 verification key"))
+}
+
+/// load groth16 proof BIN or JSON by filename
+pub fn load_groth16_proof<E: Engine>(filename: &str) -> Result<groth16::Proof<E>, Error> {
+    let reader = BufReader::new(open(filename)?);
+    if filename.ends_with(".json") {
+        let proof_str: Groth16ProofStr = serde_json::from_reader(reader).map_err(malformed("groth16 proof json"))?;
+        groth16::Proof::try_from(proof_str).map_err(malformed("groth16 proof json"))
+    } else {
+        groth16::Proof::read(reader).map_err(malformed("groth16 proof"))
+    }
+}
+
+/// load public inputs from a JSON array of decimal strings
+pub fn load_public_inputs_json<E: Engine>(filename: &str) -> Result<Vec<E::Fr>, Error> {
+    let inputs: Vec<String> = serde_json::from_reader(BufReader::new(open(filename)?)).map_err(malformed("public input json"))?;
+    inputs
+        .into_iter()
+        .map(|x| E::Fr::from_str(&x).ok_or(Error::BadFieldElement(x)))
+        .collect()
 }
 
 /// load recursive verification key file by filename
-pub fn load_recursive_verification_key(filename: &str) -> RecursiveVerificationKey<'static> {
-    let mut reader = BufReader::with_capacity(1 << 24, File::open(filename).expect("read recursive vk file err"));
-    RecursiveVerificationKey::read(&mut reader).expect("read recursive vk err")
+pub fn load_recursive_verification_key(filename: &str) -> Result<RecursiveVerificationKey<'static>, Error> {
+    Ok(load_recursive_verification_key_with_meta(filename)?.0)
+}
+
+/// load recursive verification key file by filename, together with its meta header if it has one
+pub fn load_recursive_verification_key_with_meta(
+    filename: &str,
+) -> Result<(RecursiveVerificationKey<'static>, Option<RecursiveMeta>), Error> {
+    let mut reader = BufReader::with_capacity(1 << 24, open(filename)?);
+    let meta = RecursiveMeta::read_optional(&mut reader).map_err(malformed("recursive verification key"))?;
+    let vk = RecursiveVerificationKey::read(&mut reader).map_err(malformed("recursive verification key"))?;
+    Ok((vk, meta))
+}
+
+/// load recursive proving key file by filename
+pub fn load_recursive_proving_key(filename: &str) -> Result<RecursiveProvingKey, Error> {
+    let reader = BufReader::with_capacity(1 << 29, open(filename)?);
+    RecursiveProvingKey::read(reader).map_err(malformed("recursive proving key"))
 }
 
 /// get universal setup file by filename
-fn get_universal_setup_file_buff_reader(setup_file_name: &str) -> Result<BufReader<File>, anyhow::Error> {
-    let setup_file =
-        File::open(setup_file_name).map_err(|e| format_err!("Failed to open universal setup file {}, err: {}", setup_file_name, e))?;
-    Ok(BufReader::with_capacity(1 << 29, setup_file))
+fn get_universal_setup_file_buff_reader(setup_file_name: &str) -> Result<BufReader<File>, Error> {
+    Ok(BufReader::with_capacity(1 << 29, open(setup_file_name)?))
 }
 
 /// load monomial form SRS by filename
-pub fn load_key_monomial_form<E: Engine>(filename: &str) -> Crs<E, CrsForMonomialForm> {
-    let mut buf_reader = get_universal_setup_file_buff_reader(filename).expect("read key_monomial_form file err");
-    Crs::<E, CrsForMonomialForm>::read(&mut buf_reader).expect("read key_monomial_form err")
+pub fn load_key_monomial_form<E: Engine>(filename: &str) -> Result<Crs<E, CrsForMonomialForm>, Error> {
+    let mut buf_reader = get_universal_setup_file_buff_reader(filename)?;
+    Crs::<E, CrsForMonomialForm>::read(&mut buf_reader).map_err(malformed("key_monomial_form"))
+}
+
+/// load monomial form SRS from a snarkjs Powers of Tau file, truncated to 2^power if specified
+pub fn load_key_monomial_form_from_ptau(filename: &str, power: Option<u32>) -> Result<Crs<Bn256, CrsForMonomialForm>, Error> {
+    let buf_reader = get_universal_setup_file_buff_reader(filename)?;
+    crate::ptau_file::from_reader(buf_reader, power).map_err(malformed("ptau"))
 }
 
 /// load optional lagrange form SRS by filename
-pub fn maybe_load_key_lagrange_form<E: Engine>(option_filename: Option<String>) -> Option<Crs<E, CrsForLagrangeForm>> {
+pub fn maybe_load_key_lagrange_form<E: Engine>(option_filename: Option<String>) -> Result<Option<Crs<E, CrsForLagrangeForm>>, Error> {
     match option_filename {
-        None => None,
+        None => Ok(None),
         Some(filename) => {
-            let mut buf_reader = get_universal_setup_file_buff_reader(&filename).expect("read key_lagrange_form file err");
-            let key_lagrange_form = Crs::<E, CrsForLagrangeForm>::read(&mut buf_reader).expect("read key_lagrange_form err");
-            Some(key_lagrange_form)
+            let mut buf_reader = get_universal_setup_file_buff_reader(&filename)?;
+            let key_lagrange_form = Crs::<E, CrsForLagrangeForm>::read(&mut buf_reader).map_err(malformed("key_lagrange_form"))?;
+            Ok(Some(key_lagrange_form))
         }
     }
 }
 
+/// load circom debug symbols by filename
+pub fn load_symbols(filename: &str) -> Result<CircuitSymbols, Error> {
+    CircuitSymbols::from_reader(BufReader::new(open(filename)?)).map_err(malformed("sym"))
+}
+
 /// load witness file by filename with autodetect encoding (bin or json).
-pub fn load_witness_from_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
+pub fn load_witness_from_file<E: CircomEngine>(filename: &str) -> Result<Vec<E::Fr>, Error> {
     if filename.ends_with("json") {
         load_witness_from_json_file::<E>(filename)
     } else {
@@ -98,144 +348,282 @@ pub fn load_witness_from_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
 }
 
 /// load witness from json file by filename
-pub fn load_witness_from_json_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
-    let reader = OpenOptions::new().read(true).open(filename).expect("unable to open.");
-    load_witness_from_json::<E, BufReader<File>>(BufReader::new(reader))
+pub fn load_witness_from_json_file<E: Engine>(filename: &str) -> Result<Vec<E::Fr>, Error> {
+    load_witness_from_json::<E, BufReader<File>>(BufReader::new(open(filename)?))
 }
 
 /// load witness from json by a reader
-fn load_witness_from_json<E: Engine, R: Read>(reader: R) -> Vec<E::Fr> {
-    let witness: Vec<String> = serde_json::from_reader(reader).expect("unable to read.");
-    witness.into_iter().map(|x| E::Fr::from_str(&x).unwrap()).collect::<Vec<E::Fr>>()
+fn load_witness_from_json<E: Engine, R: Read>(reader: R) -> Result<Vec<E::Fr>, Error> {
+    let witness: Vec<String> = serde_json::from_reader(reader).map_err(malformed("witness json"))?;
+    witness
+        .into_iter()
+        .map(|x| E::Fr::from_str(&x).ok_or(Error::BadFieldElement(x)))
+        .collect::<Result<Vec<E::Fr>, Error>>()
 }
 
 /// load witness from bin file by filename
-pub fn load_witness_from_bin_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
-    let reader = OpenOptions::new().read(true).open(filename).expect("unable to open.");
-    load_witness_from_bin_reader::<E, BufReader<File>>(BufReader::new(reader)).expect("read witness failed")
+pub fn load_witness_from_bin_file<E: CircomEngine>(filename: &str) -> Result<Vec<E::Fr>, Error> {
+    load_witness_from_bin_reader::<E, BufReader<File>>(BufReader::new(open(filename)?))
 }
 
 /// load witness from u8 array
-pub fn load_witness_from_array<E: Engine>(buffer: Vec<u8>) -> Result<Vec<E::Fr>, anyhow::Error> {
+pub fn load_witness_from_array<E: CircomEngine>(buffer: Vec<u8>) -> Result<Vec<E::Fr>, Error> {
     load_witness_from_bin_reader::<E, _>(buffer.as_slice())
 }
 
 /// load witness from u8 array by a reader
-fn load_witness_from_bin_reader<E: Engine, R: Read>(mut reader: R) -> Result<Vec<E::Fr>, anyhow::Error> {
+fn load_witness_from_bin_reader<E: CircomEngine, R: Read>(mut reader: R) -> Result<Vec<E::Fr>, Error> {
     let mut wtns_header = [0u8; 4];
     reader.read_exact(&mut wtns_header)?;
     if wtns_header != [119, 116, 110, 115] {
         // ruby -e 'p "wtns".bytes' => [119, 116, 110, 115]
-        bail!("invalid file header");
+        return Err(Error::BadMagic("wtns"));
     }
     let version = reader.read_u32::<LittleEndian>()?;
     log::info!("wtns version {}", version);
     if version > 2 {
-        bail!("unsupported file version");
+        return Err(Error::UnsupportedVersion { format: "wtns", version });
     }
     let num_sections = reader.read_u32::<LittleEndian>()?;
     if num_sections != 2 {
-        bail!("invalid num sections");
+        return Err(Error::SizeMismatch {
+            what: "wtns sections",
+            expected: 2,
+            actual: num_sections as u64,
+        });
     }
     // read the first section
     let sec_type = reader.read_u32::<LittleEndian>()?;
     if sec_type != 1 {
-        bail!("invalid section type");
+        return Err(Error::Malformed {
+            what: "wtns",
+            reason: format!("expected section type 1, got {}", sec_type),
+        });
     }
     let sec_size = reader.read_u64::<LittleEndian>()?;
     if sec_size != 4 + 32 + 4 {
-        bail!("invalid section len")
+        return Err(Error::SizeMismatch {
+            what: "wtns header section",
+            expected: 4 + 32 + 4,
+            actual: sec_size,
+        });
     }
     let field_size = reader.read_u32::<LittleEndian>()?;
     if field_size != 32 {
-        bail!("invalid field byte size");
+        return Err(Error::SizeMismatch {
+            what: "field element",
+            expected: 32,
+            actual: field_size as u64,
+        });
     }
     let mut prime = vec![0u8; field_size as usize];
     reader.read_exact(&mut prime)?;
-    if prime != hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430") {
-        bail!("invalid curve prime");
+    if prime != E::PRIME {
+        return Err(Error::WrongPrime {
+            prime: hex::encode(prime.iter().rev().copied().collect_vec()),
+            curve: E::CURVE,
+        });
     }
     let witness_len = reader.read_u32::<LittleEndian>()?;
     log::info!("witness len {}", witness_len);
     let sec_type = reader.read_u32::<LittleEndian>()?;
     if sec_type != 2 {
-        bail!("invalid section type");
+        return Err(Error::Malformed {
+            what: "wtns",
+            reason: format!("expected section type 2, got {}", sec_type),
+        });
     }
     let sec_size = reader.read_u64::<LittleEndian>()?;
-    if sec_size != (witness_len * field_size) as u64 {
-        bail!("invalid witness section size {}", sec_size);
+    if sec_size != witness_len as u64 * field_size as u64 {
+        return Err(Error::SizeMismatch {
+            what: "wtns witness section",
+            expected: witness_len as u64 * field_size as u64,
+            actual: sec_size,
+        });
     }
     let mut result = Vec::with_capacity(witness_len as usize);
     for _ in 0..witness_len {
         let mut repr = E::Fr::zero().into_repr();
         repr.read_le(&mut reader)?;
-        result.push(E::Fr::from_repr(repr)?);
+        result.push(E::Fr::from_repr(repr).map_err(|_| Error::BadFieldElement(format!("{}", repr)))?);
     }
     Ok(result)
 }
 
+/// load the curve a circuit is compiled for by filename. json files don't record it, and are taken as bn256
+pub fn load_curve(filename: &str) -> Result<Curve, Error> {
+    if filename.ends_with("json") {
+        Ok(Curve::Bn256)
+    } else {
+        Ok(r1cs_file::read_curve(BufReader::new(open(filename)?))?)
+    }
+}
+
 /// load r1cs file by filename with autodetect encoding (bin or json)
-pub fn load_r1cs(filename: &str) -> R1CS<Bn256> {
+pub fn load_r1cs<E: CircomEngine>(filename: &str) -> Result<R1CS<E>, Error> {
+    let (r1cs, _wire_mapping) = load_r1cs_with_wire_mapping(filename)?;
+    Ok(r1cs)
+}
+
+/// load r1cs file by filename with autodetect encoding (bin or json), together with its wire-to-label mapping.
+/// json files carry no mapping.
+pub fn load_r1cs_with_wire_mapping<E: CircomEngine>(filename: &str) -> Result<(R1CS<E>, Option<Vec<usize>>), Error> {
     if filename.ends_with("json") {
-        load_r1cs_from_json_file(filename)
+        Ok((load_r1cs_from_json_file(filename)?, None))
     } else {
-        let (r1cs, _wire_mapping) = load_r1cs_from_bin_file(filename);
-        r1cs
+        let (r1cs, wire_mapping) = load_r1cs_from_bin_file(filename)?;
+        Ok((r1cs, Some(wire_mapping)))
     }
 }
 
 /// load r1cs from json file by filename
-fn load_r1cs_from_json_file<E: Engine>(filename: &str) -> R1CS<E> {
-    let reader = OpenOptions::new().read(true).open(filename).expect("unable to open.");
-    load_r1cs_from_json(BufReader::new(reader))
+fn load_r1cs_from_json_file<E: Engine>(filename: &str) -> Result<R1CS<E>, Error> {
+    load_r1cs_from_json(BufReader::new(open(filename)?))
 }
 
 /// load r1cs from json by a reader
-fn load_r1cs_from_json<E: Engine, R: Read>(reader: R) -> R1CS<E> {
-    let circuit_json: CircuitJson = serde_json::from_reader(reader).expect("unable to read.");
+fn load_r1cs_from_json<E: Engine, R: Read>(reader: R) -> Result<R1CS<E>, Error> {
+    let circuit_json: CircuitJson = serde_json::from_reader(reader).map_err(malformed("r1cs json"))?;
 
     let num_inputs = circuit_json.num_inputs + circuit_json.num_outputs + 1;
+    if circuit_json.num_variables < num_inputs {
+        return Err(Error::SizeMismatch {
+            what: "r1cs variables",
+            expected: num_inputs as u64,
+            actual: circuit_json.num_variables as u64,
+        });
+    }
     let num_aux = circuit_json.num_variables - num_inputs;
 
     let convert_constraint = |lc: &BTreeMap<String, String>| {
         lc.iter()
-            .map(|(index, coeff)| (index.parse().unwrap(), E::Fr::from_str(coeff).unwrap()))
-            .collect_vec()
+            .map(|(index, coeff)| {
+                let index = index.parse::<usize>().map_err(malformed("r1cs json"))?;
+                let coeff = E::Fr::from_str(coeff).ok_or_else(|| Error::BadFieldElement(coeff.clone()))?;
+                Ok((index, coeff))
+            })
+            .collect::<Result<Vec<_>, Error>>()
     };
 
     let constraints = circuit_json
         .constraints
         .iter()
-        .map(|c| (convert_constraint(&c[0]), convert_constraint(&c[1]), convert_constraint(&c[2])))
-        .collect_vec();
+        .map(|c| {
+            if c.len() != 3 {
+                return Err(Error::Malformed {
+                    what: "r1cs json",
+                    reason: format!("constraint has {} linear combinations", c.len()),
+                });
+            }
+            Ok((convert_constraint(&c[0])?, convert_constraint(&c[1])?, convert_constraint(&c[2])?))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
 
-    R1CS {
+    Ok(R1CS {
         num_inputs,
+        num_outputs: circuit_json.num_outputs,
+        num_prv_inputs: circuit_json.num_prv_inputs,
         num_aux,
         num_variables: circuit_json.num_variables,
         constraints,
-    }
+        custom_gates: vec![],
+        custom_gates_applied: vec![],
+    })
 }
 
 /// load r1cs from bin file by filename
-fn load_r1cs_from_bin_file(filename: &str) -> (R1CS<Bn256>, Vec<usize>) {
-    let reader = OpenOptions::new().read(true).open(filename).expect("unable to open.");
-    load_r1cs_from_bin(BufReader::new(reader))
+fn load_r1cs_from_bin_file<E: CircomEngine>(filename: &str) -> Result<(R1CS<E>, Vec<usize>), Error> {
+    load_r1cs_from_bin(BufReader::new(open(filename)?))
 }
 
 /// load r1cs from bin by a reader
-fn load_r1cs_from_bin<R: Read + Seek>(reader: R) -> (R1CS<Bn256>, Vec<usize>) {
-    let file = crate::r1cs_file::from_reader(reader).expect("unable to read.");
+fn load_r1cs_from_bin<E: CircomEngine, R: Read + Seek>(reader: R) -> Result<(R1CS<E>, Vec<usize>), Error> {
+    let file = r1cs_file::from_reader::<E, R>(reader)?;
     let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
     let num_variables = file.header.n_wires as usize;
+    if num_variables < num_inputs {
+        return Err(Error::SizeMismatch {
+            what: "r1cs wires",
+            expected: num_inputs as u64,
+            actual: num_variables as u64,
+        });
+    }
     let num_aux = num_variables - num_inputs;
-    (
+    Ok((
         R1CS {
             num_aux,
             num_inputs,
+            num_outputs: file.header.n_pub_out as usize,
+            num_prv_inputs: file.header.n_prv_in as usize,
             num_variables,
             constraints: file.constraints,
+            custom_gates: file.custom_gates,
+            custom_gates_applied: file.custom_gates_applied,
         },
         file.wire_mapping.iter().map(|e| *e as usize).collect_vec(),
-    )
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_witness_errors() {
+        assert!(matches!(
+            load_witness_from_array::<Bn256>(b"wtnx".to_vec()),
+            Err(Error::BadMagic("wtns"))
+        ));
+
+        let mut data = b"wtns".to_vec();
+        data.extend_from_slice(&3u32.to_le_bytes());
+        assert!(matches!(
+            load_witness_from_array::<Bn256>(data),
+            Err(Error::UnsupportedVersion {
+                format: "wtns",
+                version: 3
+            })
+        ));
+
+        assert!(matches!(load_witness_from_array::<Bn256>(b"wt".to_vec()), Err(Error::Io(_))));
+
+        assert!(matches!(
+            load_witness_from_json::<Bn256, _>(&br#"["1", "x"]"#[..]),
+            Err(Error::BadFieldElement(ref x)) if x == "x"
+        ));
+    }
+
+    #[test]
+    fn test_load_r1cs_errors() {
+        let load = |data: Vec<u8>| load_r1cs_from_bin::<Bn256, _>(std::io::Cursor::new(data));
+        assert!(matches!(load(b"r1cx".to_vec()), Err(Error::BadMagic("r1cs"))));
+
+        let mut data = b"r1cs".to_vec();
+        data.extend_from_slice(&3u32.to_le_bytes());
+        assert!(matches!(
+            load(data),
+            Err(Error::UnsupportedVersion {
+                format: "r1cs",
+                version: 3
+            })
+        ));
+
+        // a header section of a bls12_381 circuit
+        let mut data = b"r1cs".to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&64u64.to_le_bytes());
+        data.extend_from_slice(&32u32.to_le_bytes());
+        data.extend_from_slice(Curve::Bls12_381.prime());
+        data.extend_from_slice(&[0u8; 28]);
+        assert!(matches!(load(data), Err(Error::WrongPrime { curve: Curve::Bn256, .. })));
+
+        assert!(matches!(load(b"r1".to_vec()), Err(Error::Malformed { what: "r1cs", .. })));
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        assert!(matches!(load_r1cs::<Bn256>("/nonexistent/circuit.r1cs"), Err(Error::Open { .. })));
+    }
 }
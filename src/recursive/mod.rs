@@ -1,4 +1,5 @@
 #![allow(clippy::needless_range_loop)]
+use crate::plonk::{self, Transcript};
 use crate::{bellman_ce, utils};
 use bellman_ce::kate_commitment::{Crs, CrsForMonomialForm};
 use bellman_ce::pairing::bn256;
@@ -19,6 +20,7 @@ use bellman_ce::plonk::{
 };
 use bellman_ce::worker::Worker;
 use bellman_ce::{Field, SynthesisError};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use franklin_crypto::plonk::circuit::bigint::field::RnsParameters;
 use franklin_crypto::plonk::circuit::verifier_circuit::affine_point_wrapper::aux_data::{AuxData, BN256AuxData};
 use franklin_crypto::plonk::circuit::verifier_circuit::data_structs::IntoLimbedWitness;
@@ -30,26 +32,253 @@ use recurisive_vk_codegen::circuit::{
     make_public_input_and_limbed_aggregate, RecursiveAggregationCircuitBn256,
 };
 pub use recurisive_vk_codegen::types::{AggregatedProof, RecursiveVerificationKey};
+use std::io::{BufRead, Write};
 
+// the depth of the vk trees of recursive artifacts without a meta header
+pub const DEFAULT_VK_TREE_DEPTH: usize = 7;
 // only support depth<8. different depths don't really make performance different
-const VK_TREE_DEPTH: usize = 7;
+pub const MAX_VK_TREE_DEPTH: usize = 7;
+
+const RECURSIVE_META_MAGIC: &[u8; 4] = b"prmt";
+const RECURSIVE_META_VERSION: u8 = 1;
+
+// the header written in front of recursive verification keys and aggregated proofs, recording the shape of the recursive circuit.
+// legacy files start with a big endian u64, so they never start with the magic
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecursiveMeta {
+    pub vk_tree_depth: usize,
+    pub num_proofs_to_check: usize,
+    pub num_inputs: usize,
+}
+
+impl RecursiveMeta {
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(RECURSIVE_META_MAGIC)?;
+        writer.write_u8(RECURSIVE_META_VERSION)?;
+        writer.write_u8(self.vk_tree_depth as u8)?;
+        writer.write_u8(self.num_proofs_to_check as u8)?;
+        writer.write_u32::<LittleEndian>(self.num_inputs as u32)
+    }
+
+    // read the header if there is one, leaving the reader at the start of the wrapped artifact
+    pub fn read_optional<R: BufRead>(mut reader: R) -> std::io::Result<Option<Self>> {
+        if !reader.fill_buf()?.starts_with(RECURSIVE_META_MAGIC) {
+            return Ok(None);
+        }
+        reader.consume(RECURSIVE_META_MAGIC.len());
+        let version = reader.read_u8()?;
+        if version == 0 || version > RECURSIVE_META_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported recursive meta version {}", version),
+            ));
+        }
+        Ok(Some(RecursiveMeta {
+            vk_tree_depth: reader.read_u8()? as usize,
+            num_proofs_to_check: reader.read_u8()? as usize,
+            num_inputs: reader.read_u32::<LittleEndian>()? as usize,
+        }))
+    }
+
+    // the shape of the recursive circuit which produced an aggregated proof
+    pub fn of_proof(proof: &AggregatedProof, vk_tree_depth: usize) -> Self {
+        RecursiveMeta {
+            vk_tree_depth,
+            num_proofs_to_check: proof.individual_vk_idxs.len(),
+            num_inputs: proof.individual_num_inputs,
+        }
+    }
+}
+
+// check that an aggregated proof comes from the recursive circuit of a verification key
+pub fn check_metas(vk_meta: Option<&RecursiveMeta>, proof_meta: Option<&RecursiveMeta>) -> Result<(), anyhow::Error> {
+    if let (Some(vk_meta), Some(proof_meta)) = (vk_meta, proof_meta) {
+        anyhow::ensure!(
+            vk_meta.vk_tree_depth == proof_meta.vk_tree_depth,
+            invalid_input!(
+                "proof is aggregated with a vk tree of depth {}, but the verification key is for depth {}",
+                proof_meta.vk_tree_depth,
+                vk_meta.vk_tree_depth
+            )
+        );
+        anyhow::ensure!(
+            vk_meta.num_proofs_to_check == proof_meta.num_proofs_to_check && vk_meta.num_inputs == proof_meta.num_inputs,
+            invalid_input!(
+                "proof aggregates {} proofs of {} inputs, but the verification key is for {} proofs of {} inputs",
+                proof_meta.num_proofs_to_check,
+                proof_meta.num_inputs,
+                vk_meta.num_proofs_to_check,
+                vk_meta.num_inputs
+            )
+        );
+    }
+    Ok(())
+}
+
+fn check_vk_tree_depth(vk_tree_depth: usize) -> Result<(), anyhow::Error> {
+    anyhow::ensure!(
+        (1..=MAX_VK_TREE_DEPTH).contains(&vk_tree_depth),
+        invalid_input!("vk tree depth must be between 1 and {}, got {}", MAX_VK_TREE_DEPTH, vk_tree_depth)
+    );
+    Ok(())
+}
+
+fn check_num_proofs_to_check(num_proofs_to_check: usize) -> Result<(), anyhow::Error> {
+    anyhow::ensure!(num_proofs_to_check > 0, invalid_input!("no proof to aggregate"));
+    anyhow::ensure!(
+        num_proofs_to_check < 256,
+        invalid_input!("at most 255 proofs can be aggregated, got {}", num_proofs_to_check)
+    );
+    Ok(())
+}
+
+// check the proofs can be aggregated, each of them with the vk at its index in `old_vks`, and return their number of inputs
+fn check_old_proofs(
+    old_proofs: &[OldProof<Bn256, PlonkCsWidth4WithNextStepParams>],
+    old_vks: &[OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>],
+    vk_indexes: &[usize],
+    vk_tree_depth: usize,
+) -> Result<usize, anyhow::Error> {
+    check_num_proofs_to_check(old_proofs.len())?;
+    check_old_vks(old_vks, vk_tree_depth)?;
+    anyhow::ensure!(
+        vk_indexes.len() == old_proofs.len(),
+        invalid_input!("{} vk indexes are given for {} proofs", vk_indexes.len(), old_proofs.len())
+    );
+    let num_inputs = old_proofs[0].num_inputs;
+    for (i, (p, &vk_index)) in old_proofs.iter().zip(vk_indexes).enumerate() {
+        anyhow::ensure!(p.num_inputs == num_inputs, invalid_input!("proofs num_inputs mismatch!"));
+        anyhow::ensure!(
+            vk_index < old_vks.len(),
+            invalid_input!("vk index {} of proof {} is out of the {} vks", vk_index, i, old_vks.len())
+        );
+        anyhow::ensure!(
+            old_vks[vk_index].num_inputs == num_inputs,
+            invalid_input!(
+                "proof {} has {} inputs, but its vk {} expects {}",
+                i,
+                num_inputs,
+                vk_index,
+                old_vks[vk_index].num_inputs
+            )
+        );
+    }
+    Ok(num_inputs)
+}
+
+fn check_old_vks(
+    old_vks: &[OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>],
+    vk_tree_depth: usize,
+) -> Result<(), anyhow::Error> {
+    check_vk_tree_depth(vk_tree_depth)?;
+    anyhow::ensure!(!old_vks.is_empty(), invalid_input!("no vk to build the vk tree of"));
+    anyhow::ensure!(
+        old_vks.len() <= 1 << vk_tree_depth,
+        invalid_input!(
+            "a vk tree of depth {} holds at most {} vks, got {}",
+            vk_tree_depth,
+            1 << vk_tree_depth,
+            old_vks.len()
+        )
+    );
+    Ok(())
+}
+
+// the setup of a recursive circuit, which takes minutes to compute on a big srs, together with the shape of the circuit
+pub struct RecursiveProvingKey {
+    pub meta: RecursiveMeta,
+    pub setup: Setup<Bn256, RecursiveAggregationCircuitBn256<'static>>,
+}
+
+impl RecursiveProvingKey {
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        self.meta.write(&mut writer)?;
+        self.setup.write(writer)
+    }
+
+    pub fn read<R: BufRead>(mut reader: R) -> std::io::Result<Self> {
+        let meta = RecursiveMeta::read_optional(&mut reader)?
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing recursive meta header"))?;
+        let setup = Setup::read(reader)?;
+        Ok(RecursiveProvingKey { meta, setup })
+    }
+}
+
+// a valid proof filling the slots of a recursive circuit which a batch of proofs leaves empty
+pub struct Padding {
+    pub proof: OldProof<Bn256, PlonkCsWidth4WithNextStepParams>,
+    // the index of the vk of the padding proof in the vk tree
+    pub vk_index: usize,
+}
 
-// recursively prove multiple proofs, and aggregate them into one
+// append copies of the padding proof to a batch of proofs, up to num_proofs_to_check
+pub fn pad(
+    old_proofs: &mut Vec<OldProof<Bn256, PlonkCsWidth4WithNextStepParams>>,
+    vk_indexes: &mut Vec<usize>,
+    num_proofs_to_check: usize,
+    padding: Option<&Padding>,
+) -> Result<(), anyhow::Error> {
+    anyhow::ensure!(
+        vk_indexes.len() == old_proofs.len(),
+        invalid_input!("{} vk indexes are given for {} proofs", vk_indexes.len(), old_proofs.len())
+    );
+    anyhow::ensure!(
+        old_proofs.len() <= num_proofs_to_check,
+        invalid_input!(
+            "{} proofs are more than the {} the recursive circuit checks",
+            old_proofs.len(),
+            num_proofs_to_check
+        )
+    );
+    if old_proofs.len() == num_proofs_to_check {
+        return Ok(());
+    }
+    let padding = padding.ok_or_else(|| {
+        invalid_input!(
+            "a padding proof is needed to fill {} proofs up to {}",
+            old_proofs.len(),
+            num_proofs_to_check
+        )
+    })?;
+    old_proofs.resize(num_proofs_to_check, padding.proof.clone());
+    vk_indexes.resize(num_proofs_to_check, padding.vk_index);
+    Ok(())
+}
+
+// recursively prove multiple proofs, and aggregate them into one. proof i is verified with old_vks[vk_indexes[i]],
+// and the batch is padded up to num_proofs_to_check. the recursive circuit is set up unless its proving key is given
+#[allow(clippy::too_many_arguments)]
 pub fn prove(
     big_crs: Crs<Bn256, CrsForMonomialForm>,
-    old_proofs: Vec<OldProof<Bn256, PlonkCsWidth4WithNextStepParams>>,
-    old_vk: OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
-) -> Result<AggregatedProof, SynthesisError> {
+    mut old_proofs: Vec<OldProof<Bn256, PlonkCsWidth4WithNextStepParams>>,
+    old_vks: &[OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>],
+    vk_indexes: &[usize],
+    num_proofs_to_check: usize,
+    padding: Option<&Padding>,
+    vk_tree_depth: usize,
+    proving_key: Option<&RecursiveProvingKey>,
+) -> Result<AggregatedProof, anyhow::Error> {
+    let mut vk_indexes = vk_indexes.to_vec();
+    if let Some(padding) = padding {
+        anyhow::ensure!(
+            padding.vk_index < old_vks.len(),
+            invalid_input!("padding vk index {} is out of the {} vks", padding.vk_index, old_vks.len())
+        );
+        // an invalid proof would leave the recursive circuit unsatisfied
+        anyhow::ensure!(
+            plonk::verify(&old_vks[padding.vk_index], &padding.proof, Transcript::Rescue)?,
+            invalid_input!("padding proof is not valid for vk {} with the rescue transcript", padding.vk_index)
+        );
+    }
+    pad(&mut old_proofs, &mut vk_indexes, num_proofs_to_check, padding)?;
+    let vk_indexes = &vk_indexes[..];
+    let num_inputs = check_old_proofs(&old_proofs, old_vks, vk_indexes, vk_tree_depth)?;
     let num_proofs_to_check = old_proofs.len();
-    assert!(num_proofs_to_check > 0);
-    assert!(num_proofs_to_check < 256);
     let mut individual_vk_inputs = Vec::new();
-    let num_inputs = old_proofs[0].num_inputs;
     for p in &old_proofs {
         for input_value in p.input_values.clone() {
             individual_vk_inputs.push(input_value);
         }
-        assert_eq!(p.num_inputs, num_inputs, "proofs num_inputs mismatch!");
     }
 
     let worker = Worker::new();
@@ -60,22 +289,20 @@ pub fn prove(
     g2_bases.copy_from_slice(&big_crs.g2_monomial_bases.as_ref()[..]);
     let aux_data = BN256AuxData::new();
 
-    //notice we have only 1 vk now
-    let vks = old_proofs.iter().map(|_| old_vk.clone()).collect_vec();
-    let individual_vk_idxs = old_proofs.iter().map(|_| 0usize).collect_vec();
-    let (_, (vks_tree, all_witness_values)) = create_vks_tree(&[old_vk], VK_TREE_DEPTH)?;
+    // the vk of every proof, and the path to its leaf in the vk tree
+    let vks = vk_indexes.iter().map(|&i| old_vks[i].clone()).collect_vec();
+    let individual_vk_idxs = vk_indexes.to_vec();
+    let (_, (vks_tree, all_witness_values)) = create_vks_tree(old_vks, vk_tree_depth)?;
     let vks_tree_root = vks_tree.get_commitment();
 
     let proof_ids = individual_vk_idxs.clone();
 
     let mut queries = vec![];
-    for proof_id in 0..num_proofs_to_check {
-        let vk = &vks[individual_vk_idxs[proof_id]];
-
+    for (vk, &vk_index) in vks.iter().zip(vk_indexes) {
         let leaf_values = vk.into_witness_for_params(&rns_params).expect("must transform into limbed witness");
 
         let values_per_leaf = leaf_values.len();
-        let intra_leaf_indexes_to_query: Vec<_> = ((proof_id * values_per_leaf)..((proof_id + 1) * values_per_leaf)).collect();
+        let intra_leaf_indexes_to_query: Vec<_> = ((vk_index * values_per_leaf)..((vk_index + 1) * values_per_leaf)).collect();
         let q = vks_tree.produce_query(intra_leaf_indexes_to_query, &all_witness_values);
 
         assert_eq!(q.values(), &leaf_values[..]);
@@ -90,7 +317,7 @@ pub fn prove(
     let circuit = RecursiveAggregationCircuitBn256 {
         num_proofs_to_check,
         num_inputs,
-        vk_tree_depth: VK_TREE_DEPTH,
+        vk_tree_depth,
         vk_root: Some(vks_tree_root),
         vk_witnesses: Some(vks),
         vk_auth_paths: Some(queries),
@@ -117,14 +344,40 @@ pub fn prove(
     log::info!("satisfied {}", cs.is_satisfied());
     assert_eq!(cs.num_inputs, 1);
 
-    let setup: Setup<Bn256, RecursiveAggregationCircuitBn256> =
-        create_recursive_circuit_setup(num_proofs_to_check, num_inputs, VK_TREE_DEPTH)?;
+    let meta = RecursiveMeta {
+        vk_tree_depth,
+        num_proofs_to_check,
+        num_inputs,
+    };
+    let computed_setup: Setup<Bn256, RecursiveAggregationCircuitBn256<'static>>;
+    let setup = match proving_key {
+        Some(proving_key) => {
+            anyhow::ensure!(
+                proving_key.meta == meta,
+                invalid_input!(
+                    "recursive proving key is for {} proofs of {} inputs with a vk tree of depth {}, \
+                     but they are {} proofs of {} inputs with depth {}",
+                    proving_key.meta.num_proofs_to_check,
+                    proving_key.meta.num_inputs,
+                    proving_key.meta.vk_tree_depth,
+                    num_proofs_to_check,
+                    num_inputs,
+                    vk_tree_depth
+                )
+            );
+            &proving_key.setup
+        }
+        None => {
+            computed_setup = create_recursive_circuit_setup(num_proofs_to_check, num_inputs, vk_tree_depth)?;
+            &computed_setup
+        }
+    };
 
     let mut assembly = ProvingAssembly::<Bn256, PlonkCsWidth4WithNextStepAndCustomGatesParams, Width4MainGateWithDNext>::new();
     circuit.synthesize(&mut assembly).expect("must synthesize");
     assembly.finalize();
 
-    let proof = assembly.create_proof::<_, RollingKeccakTranscript<<Bn256 as ScalarEngine>::Fr>>(&worker, &setup, &big_crs, None)?;
+    let proof = assembly.create_proof::<_, RollingKeccakTranscript<<Bn256 as ScalarEngine>::Fr>>(&worker, setup, &big_crs, None)?;
 
     Ok(AggregatedProof {
         proof,
@@ -196,43 +449,129 @@ pub fn verify(
 pub fn export_vk(
     num_proofs_to_check: usize,
     num_inputs: usize,
+    vk_tree_depth: usize,
     big_crs: &Crs<Bn256, CrsForMonomialForm>,
 ) -> Result<VerificationKey<Bn256, RecursiveAggregationCircuitBn256>, anyhow::Error> {
-    let (recursive_circuit_vk, _recursive_circuit_setup) =
-        create_recursive_circuit_vk_and_setup(num_proofs_to_check, num_inputs, VK_TREE_DEPTH, big_crs)?;
-    Ok(recursive_circuit_vk)
+    Ok(export_vk_and_proving_key(num_proofs_to_check, num_inputs, vk_tree_depth, big_crs)?.0)
+}
+
+// export a verification key for a recursion circuit, and the proving key of the setup computed along the way
+pub fn export_vk_and_proving_key(
+    num_proofs_to_check: usize,
+    num_inputs: usize,
+    vk_tree_depth: usize,
+    big_crs: &Crs<Bn256, CrsForMonomialForm>,
+) -> Result<(RecursiveVerificationKey<'static>, RecursiveProvingKey), anyhow::Error> {
+    check_num_proofs_to_check(num_proofs_to_check)?;
+    check_vk_tree_depth(vk_tree_depth)?;
+    let (recursive_circuit_vk, recursive_circuit_setup) =
+        create_recursive_circuit_vk_and_setup(num_proofs_to_check, num_inputs, vk_tree_depth, big_crs)?;
+    let proving_key = RecursiveProvingKey {
+        meta: RecursiveMeta {
+            vk_tree_depth,
+            num_proofs_to_check,
+            num_inputs,
+        },
+        setup: recursive_circuit_setup,
+    };
+    Ok((recursive_circuit_vk, proving_key))
 }
 
 // hash the vk_tree root, proof_indexes, proofs' inputs and aggregated points
 pub fn get_aggregated_input(
     old_proofs: Vec<OldProof<Bn256, PlonkCsWidth4WithNextStepParams>>,
-    old_vk: OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
+    old_vks: &[OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>],
+    vk_indexes: &[usize],
+    vk_tree_depth: usize,
 ) -> Result<bn256::Fr, anyhow::Error> {
-    let num_proofs_to_check = old_proofs.len();
-    assert!(num_proofs_to_check > 0);
-    assert!(num_proofs_to_check < 256);
-    let num_inputs = old_proofs[0].num_inputs;
-    for p in &old_proofs {
-        assert_eq!(p.num_inputs, num_inputs, "proofs num_inputs mismatch!");
-    }
+    check_old_proofs(&old_proofs, old_vks, vk_indexes, vk_tree_depth)?;
 
     let rns_params = RnsParameters::<Bn256, <Bn256 as Engine>::Fq>::new_for_field(68, 110, 4);
     let rescue_params = Bn256RescueParams::new_checked_2_into_1();
 
-    let vks = old_proofs.iter().map(|_| old_vk.clone()).collect_vec();
-    let proof_ids = (0..num_proofs_to_check).map(|_| 0usize).collect_vec();
+    let vks = vk_indexes.iter().map(|&i| old_vks[i].clone()).collect_vec();
 
-    let (_, (vks_tree, _)) = create_vks_tree(&[old_vk], VK_TREE_DEPTH)?;
-    let vks_tree_root = vks_tree.get_commitment();
+    let vks_tree_root = get_vk_tree_root_hash(old_vks, vk_tree_depth)?;
 
     let aggregate = make_aggregate(&old_proofs, &vks, &rescue_params, &rns_params)?;
 
-    let (expected_input, _) = make_public_input_and_limbed_aggregate(vks_tree_root, &proof_ids, &old_proofs, &aggregate, &rns_params);
+    let (expected_input, _) = make_public_input_and_limbed_aggregate(vks_tree_root, vk_indexes, &old_proofs, &aggregate, &rns_params);
 
     Ok(expected_input)
 }
 
-pub fn get_vk_tree_root_hash(old_vk: OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>) -> Result<bn256::Fr, anyhow::Error> {
-    let (_, (vks_tree, _)) = create_vks_tree(&vec![old_vk], VK_TREE_DEPTH)?;
+// the root of the tree of vks, whose leaves past the given vks repeat the last one
+pub fn get_vk_tree_root_hash(
+    old_vks: &[OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>],
+    vk_tree_depth: usize,
+) -> Result<bn256::Fr, anyhow::Error> {
+    check_old_vks(old_vks, vk_tree_depth)?;
+    let (_, (vks_tree, _)) = create_vks_tree(old_vks, vk_tree_depth)?;
     Ok(vks_tree.get_commitment())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursive_meta() {
+        let meta = RecursiveMeta {
+            vk_tree_depth: 3,
+            num_proofs_to_check: 8,
+            num_inputs: 2,
+        };
+        let mut buf = vec![];
+        meta.write(&mut buf).unwrap();
+        buf.extend_from_slice(b"body");
+        let mut reader = &buf[..];
+        assert_eq!(RecursiveMeta::read_optional(&mut reader).unwrap(), Some(meta));
+        assert_eq!(reader, b"body");
+
+        let legacy = 1u64.to_be_bytes();
+        let mut reader = &legacy[..];
+        assert_eq!(RecursiveMeta::read_optional(&mut reader).unwrap(), None);
+        assert_eq!(reader, &legacy[..]);
+        // proving keys always have the header
+        assert!(RecursiveProvingKey::read(&legacy[..]).is_err());
+
+        assert!(check_metas(Some(&meta), Some(&meta)).is_ok());
+        assert!(check_metas(Some(&meta), None).is_ok());
+        let deeper = RecursiveMeta { vk_tree_depth: 7, ..meta };
+        assert!(check_metas(Some(&meta), Some(&deeper)).is_err());
+        let fewer = RecursiveMeta {
+            num_proofs_to_check: 4,
+            ..meta
+        };
+        assert!(check_metas(Some(&meta), Some(&fewer)).is_err());
+        assert!(check_vk_tree_depth(0).is_err());
+        assert!(check_vk_tree_depth(MAX_VK_TREE_DEPTH + 1).is_err());
+    }
+
+    #[test]
+    fn test_recursive_proving_key() {
+        let meta = RecursiveMeta {
+            vk_tree_depth: 1,
+            num_proofs_to_check: 1,
+            num_inputs: 1,
+        };
+        let mut setup = Setup::empty();
+        setup.n = 3;
+        setup.num_inputs = 1;
+        setup.state_width = 4;
+        let proving_key = RecursiveProvingKey { meta, setup };
+        let mut buf = vec![];
+        proving_key.write(&mut buf).unwrap();
+
+        let read = RecursiveProvingKey::read(&buf[..]).unwrap();
+        assert_eq!(read.meta, meta);
+        assert_eq!(read.setup.n, proving_key.setup.n);
+        assert_eq!(read.setup.num_inputs, proving_key.setup.num_inputs);
+        let mut reread = vec![];
+        read.write(&mut reread).unwrap();
+        assert_eq!(reread, buf);
+
+        // a truncated key is rejected
+        assert!(RecursiveProvingKey::read(&buf[..buf.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+// a long running prover serving prove, verify and analyse over http, which keeps the setup of the circuits it has
+// proven recently in memory, so that only the first proof of a circuit pays for loading and transpiling it
+use crate::bellman_ce::kate_commitment::{Crs, CrsForMonomialForm};
+use crate::bellman_ce::plonk::better_cs::{cs::PlonkCsWidth4WithNextStepParams, keys::Proof};
+use crate::circom_circuit::{self, CircomCircuit};
+use crate::plonk::{self, ArtifactMeta, PlonkEngine, SetupForProver, Transcript};
+use crate::reader;
+use crate::witness::{self, WitnessCalculator};
+use crate::{ArtifactMetaStr, ProofStr};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Instant, SystemTime};
+
+#[derive(Deserialize)]
+pub struct ProveRequest {
+    // circuit R1CS or JSON file, which is also the key of the setup cache
+    pub circuit: String,
+    // witness BIN or JSON file
+    #[serde(default)]
+    pub witness: Option<String>,
+    // circuit inputs, to calculate the witness with `wasm` instead of reading `witness`
+    #[serde(default)]
+    pub input: Option<BTreeMap<String, serde_json::Value>>,
+    #[serde(default)]
+    pub wasm: Option<String>,
+    // keccak or rescue, keccak by default
+    #[serde(default)]
+    pub transcript: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyRequest {
+    // verification key BIN or JSON file
+    pub verification_key: String,
+    pub proof: ProofStr,
+    // the transcript recorded in the proof by default, or keccak
+    #[serde(default)]
+    pub transcript: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct VerifyResponse {
+    pub valid: bool,
+    pub transcript: String,
+}
+
+#[derive(Deserialize)]
+pub struct AnalyseRequest {
+    pub circuit: String,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+// the setup of a circuit, valid as long as the circuit file is not modified
+struct CachedSetup<E: PlonkEngine> {
+    modified: SystemTime,
+    circuit: CircomCircuit<E>,
+    circuit_digest: [u8; 32],
+    setup: SetupForProver<E>,
+    // the wasm last used to calculate the witness of the circuit, locked while it runs
+    calculator: Mutex<Option<CachedCalculator>>,
+}
+
+// a compiled wasm, valid as long as its file is not modified
+struct CachedCalculator {
+    wasm: String,
+    modified: SystemTime,
+    calculator: WitnessCalculator,
+}
+
+impl<E: PlonkEngine> CachedSetup<E> {
+    // calculate a witness with the cached wasm, compiling it first if it's another or modified one
+    fn calculate_witness(&self, wasm: &str, input: BTreeMap<String, serde_json::Value>) -> Result<Vec<E::Fr>, anyhow::Error> {
+        let inputs = witness::parse_inputs(input)?;
+        let modified = std::fs::metadata(wasm)?.modified()?;
+        let mut calculator = lock(&self.calculator);
+        if !matches!(calculator.as_ref(), Some(c) if c.wasm == wasm && c.modified == modified) {
+            log::info!("Compiling {}...", wasm);
+            *calculator = Some(CachedCalculator {
+                wasm: wasm.to_owned(),
+                modified,
+                calculator: WitnessCalculator::from_file(wasm)?,
+            });
+        }
+        let calculator = &calculator.as_ref().expect("the calculator is just cached").calculator;
+        calculator.calculate_witness::<E>(&inputs, true)
+    }
+}
+
+type SetupSlot<E> = Arc<Mutex<Option<Arc<CachedSetup<E>>>>>;
+
+pub struct ProverOptions {
+    // the directory every file named in a request must be in, which relative names are taken from
+    pub root: PathBuf,
+    // circuit setups kept in memory, the least recently used one is dropped past it
+    pub max_cached: usize,
+}
+
+pub struct Prover<E: PlonkEngine> {
+    key_monomial_form: Crs<E, CrsForMonomialForm>,
+    root: PathBuf,
+    max_cached: usize,
+    // one slot per circuit file, so that different circuits are set up concurrently but the same one only once,
+    // with the last time it was used
+    setups: Mutex<HashMap<String, (SetupSlot<E>, Instant)>>,
+}
+
+// a panicking job must not take the cache down with it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<E: PlonkEngine> Prover<E> {
+    pub fn new(key_monomial_form: Crs<E, CrsForMonomialForm>, options: ProverOptions) -> Result<Self, anyhow::Error> {
+        let root = options
+            .root
+            .canonicalize()
+            .map_err(|e| invalid_input!("invalid root directory {}: {}", options.root.display(), e))?;
+        Ok(Prover {
+            key_monomial_form,
+            root,
+            max_cached: options.max_cached.max(1),
+            setups: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // the canonical path of a file named in a request, which must be under the root directory
+    fn resolve(&self, file: &str) -> Result<String, anyhow::Error> {
+        let path = self
+            .root
+            .join(file)
+            .canonicalize()
+            .map_err(|e| invalid_input!("invalid file {}: {}", file, e))?;
+        anyhow::ensure!(
+            path.starts_with(&self.root),
+            invalid_input!("{} is outside of the served directory {}", file, self.root.display())
+        );
+        path.to_str()
+            .map(str::to_owned)
+            .ok_or_else(|| invalid_input!("invalid file name {}", path.display()))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn num_cached(&self) -> usize {
+        lock(&self.setups).len()
+    }
+
+    // every cached setup shares the bases of the srs instead of copying them
+    fn shared_key_monomial_form(&self) -> Crs<E, CrsForMonomialForm> {
+        Crs::new_in_arc(
+            self.key_monomial_form.g1_bases.clone(),
+            self.key_monomial_form.g2_monomial_bases.clone(),
+        )
+    }
+
+    // the slot of a circuit, dropping the least recently used ones past max_cached. jobs running on a dropped setup
+    // keep it until they are done
+    fn slot(&self, circuit_file: &str) -> SetupSlot<E> {
+        let mut setups = lock(&self.setups);
+        let (slot, used) = setups
+            .entry(circuit_file.to_owned())
+            .or_insert_with(|| (Default::default(), Instant::now()));
+        *used = Instant::now();
+        let slot = slot.clone();
+        while setups.len() > self.max_cached {
+            let evicted = match setups.iter().min_by_key(|(_, (_, used))| *used) {
+                Some((file, _)) => file.clone(),
+                None => break,
+            };
+            log::info!("Dropping the setup of {}", evicted);
+            setups.remove(&evicted);
+        }
+        slot
+    }
+
+    fn setup(&self, circuit_file: &str) -> Result<Arc<CachedSetup<E>>, anyhow::Error> {
+        let modified = std::fs::metadata(circuit_file)?.modified()?;
+        let slot = self.slot(circuit_file);
+        let mut slot = lock(&slot);
+        if let Some(cached) = slot.as_ref().filter(|cached| cached.modified == modified) {
+            return Ok(cached.clone());
+        }
+        log::info!("Loading circuit from {}...", circuit_file);
+        let (r1cs, wire_mapping) = reader::load_r1cs_with_wire_mapping::<E>(circuit_file)?;
+        r1cs.ensure_no_custom_gates()?;
+        let circuit = CircomCircuit {
+            r1cs,
+            witness: None,
+            wire_mapping,
+            aux_offset: plonk::AUX_OFFSET,
+            symbols: None,
+        };
+        let setup = SetupForProver::prepare_setup_for_prover(circuit.clone(), self.shared_key_monomial_form(), None)?;
+        let cached = Arc::new(CachedSetup {
+            modified,
+            circuit_digest: circuit.r1cs.digest(),
+            circuit,
+            setup,
+            calculator: Mutex::new(None),
+        });
+        *slot = Some(cached.clone());
+        Ok(cached)
+    }
+
+    pub fn prove(&self, request: ProveRequest) -> Result<ProofStr, anyhow::Error> {
+        let transcript: Transcript = request.transcript.as_deref().unwrap_or("keccak").parse()?;
+        let cached = self.setup(&self.resolve(&request.circuit)?)?;
+        let witness = match (&request.witness, request.input, &request.wasm) {
+            (Some(witness), None, None) => reader::load_witness_from_file::<E>(&self.resolve(witness)?)?,
+            (None, Some(input), Some(wasm)) => cached.calculate_witness(&self.resolve(wasm)?, input)?,
+            _ => anyhow::bail!(invalid_input!("either witness, or input together with wasm, is required")),
+        };
+        let mut circuit = cached.circuit.clone();
+        circuit.wire_mapping = circom_circuit::wire_mapping_for_witness(circuit.wire_mapping, witness.len(), circuit.r1cs.num_variables)?;
+        circuit.witness = Some(witness);
+
+        log::info!("Proving {}...", request.circuit);
+        let proof = cached.setup.prove(circuit, transcript)?;
+        let meta = ArtifactMeta {
+            curve: E::CURVE,
+            transcript: Some(transcript),
+            circuit_digest: cached.circuit_digest,
+            srs_power: cached.setup.srs_power(),
+        };
+        Ok(ProofStr {
+            meta: Some(ArtifactMetaStr::from(&meta)),
+            ..ProofStr::from(&proof)
+        })
+    }
+
+    pub fn verify(&self, request: VerifyRequest) -> Result<VerifyResponse, anyhow::Error> {
+        let verification_key = self.resolve(&request.verification_key)?;
+        let (vk, vk_meta) = if verification_key.ends_with(".json") {
+            reader::load_verification_key_json_with_meta::<E>(&verification_key)?
+        } else {
+            reader::load_verification_key_with_meta::<E>(&verification_key)?
+        };
+        let proof_meta = request.proof.meta.as_ref().map(ArtifactMeta::try_from).transpose()?;
+        let transcript = request.transcript.as_deref().map(str::parse).transpose()?;
+        let transcript = plonk::check_artifacts(transcript, vk_meta.as_ref(), proof_meta.as_ref())?;
+        let proof = Proof::<E, PlonkCsWidth4WithNextStepParams>::try_from(request.proof)?;
+        Ok(VerifyResponse {
+            valid: plonk::verify(&vk, &proof, transcript)?,
+            transcript: transcript.to_string(),
+        })
+    }
+
+    pub fn analyse(&self, request: AnalyseRequest) -> Result<plonk::AnalyseResult, anyhow::Error> {
+        let circuit = CircomCircuit {
+            r1cs: reader::load_r1cs::<E>(&self.resolve(&request.circuit)?)?,
+            witness: None,
+            wire_mapping: None,
+            aux_offset: plonk::AUX_OFFSET,
+            symbols: None,
+        };
+        plonk::analyse(circuit)
+    }
+
+    // route a request, and return the status code and json body of the response
+    pub fn handle(&self, method: &str, url: &str, body: &[u8]) -> (u16, String) {
+        fn respond<Req: DeserializeOwned, Resp: Serialize>(
+            body: &[u8],
+            handler: impl FnOnce(Req) -> Result<Resp, anyhow::Error>,
+        ) -> (u16, String) {
+            let request = match serde_json::from_slice(body) {
+                Ok(request) => request,
+                Err(e) => return error(400, format!("invalid request: {}", e)),
+            };
+            match catch_unwind(AssertUnwindSafe(|| handler(request))) {
+                Ok(Ok(response)) => (200, serde_json::to_string(&response).unwrap()),
+                Ok(Err(e)) => error(422, format!("{:#}", e)),
+                Err(_) => error(500, "the job panicked, see the server log".to_owned()),
+            }
+        }
+        fn error(status: u16, error: String) -> (u16, String) {
+            (status, serde_json::to_string(&ErrorResponse { error }).unwrap())
+        }
+
+        match (method, url) {
+            ("POST", "/prove") => respond(body, |request| self.prove(request)),
+            ("POST", "/verify") => respond(body, |request| self.verify(request)),
+            ("POST", "/analyse") => respond(body, |request| self.analyse(request)),
+            _ => error(404, format!("no such endpoint: {} {}", method, url)),
+        }
+    }
+}
+
+pub struct ServerOptions {
+    pub address: String,
+    // jobs run at the same time, each of them proving on all cores
+    pub jobs: usize,
+    // jobs waiting for a runner, more are rejected with 503
+    pub queue: usize,
+}
+
+// serve until the process is killed
+pub fn serve<E: PlonkEngine>(prover: Prover<E>, options: &ServerOptions) -> Result<(), anyhow::Error> {
+    let server =
+        tiny_http::Server::http(&options.address).map_err(|e| anyhow::format_err!("failed to listen on {}: {}", options.address, e))?;
+    let prover = Arc::new(prover);
+    let (sender, receiver) = sync_channel::<tiny_http::Request>(options.queue);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..options.jobs.max(1) {
+        let (prover, receiver) = (prover.clone(), receiver.clone());
+        std::thread::spawn(move || loop {
+            let request = match lock(&receiver).recv() {
+                Ok(request) => request,
+                Err(_) => return,
+            };
+            handle(&prover, request);
+        });
+    }
+    log::info!("Serving on {} with {} jobs", options.address, options.jobs.max(1));
+    for request in server.incoming_requests() {
+        if let Err(TrySendError::Full(request)) = sender.try_send(request) {
+            log::warn!("job queue is full, rejecting {} {}", request.method(), request.url());
+            let body = serde_json::to_string(&ErrorResponse {
+                error: "too many jobs queued".to_owned(),
+            })?;
+            send(request, 503, body);
+        }
+    }
+    Ok(())
+}
+
+fn handle<E: PlonkEngine>(prover: &Prover<E>, mut request: tiny_http::Request) {
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().read_to_end(&mut body) {
+        log::warn!("failed to read request body: {}", e);
+        return;
+    }
+    let started = std::time::Instant::now();
+    let (status, response) = prover.handle(request.method().as_str(), request.url(), &body);
+    log::info!("{} {} {} in {:?}", request.method(), request.url(), status, started.elapsed());
+    send(request, status, response);
+}
+
+fn send(request: tiny_http::Request, status: u16, body: String) {
+    let content_type = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = tiny_http::Response::from_string(body)
+        .with_status_code(status)
+        .with_header(content_type);
+    if let Err(e) = request.respond(response) {
+        log::warn!("failed to send response: {}", e);
+    }
+}
@@ -0,0 +1,109 @@
+// signal names from the `.sym` file generated by circom alongside the r1cs.
+// each line is `label,wire,component,name`, where wire is -1 for the signals removed by the optimizer
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub label: usize,
+    pub wire: Option<usize>,
+    pub component: usize,
+    // the full signal path, like `main.hasher.out`
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CircuitSymbols {
+    symbols: Vec<Symbol>,
+    // label -> position in `symbols`
+    labels: BTreeMap<usize, usize>,
+    // the optimizer can merge several signals into one wire
+    wires: BTreeMap<usize, Vec<usize>>,
+}
+
+impl CircuitSymbols {
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, anyhow::Error> {
+        let mut symbols = CircuitSymbols::default();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(4, ',').collect();
+            anyhow::ensure!(
+                fields.len() == 4,
+                invalid_input!("line {}: expected `label,wire,component,name`", line_number + 1)
+            );
+            let parse = |field: &str, what: &str| -> Result<i64, anyhow::Error> {
+                field
+                    .parse::<i64>()
+                    .map_err(|e| invalid_input!("line {}: invalid {} {}: {}", line_number + 1, what, field, e))
+            };
+            let label = parse(fields[0], "label")?;
+            let wire = parse(fields[1], "wire")?;
+            let component = parse(fields[2], "component")?;
+            anyhow::ensure!(
+                label >= 0 && component >= 0,
+                invalid_input!("line {}: negative label or component", line_number + 1)
+            );
+            symbols.push(Symbol {
+                label: label as usize,
+                wire: if wire >= 0 { Some(wire as usize) } else { None },
+                component: component as usize,
+                name: fields[3].to_string(),
+            });
+        }
+        Ok(symbols)
+    }
+
+    fn push(&mut self, symbol: Symbol) {
+        let position = self.symbols.len();
+        self.labels.insert(symbol.label, position);
+        if let Some(wire) = symbol.wire {
+            self.wires.entry(wire).or_default().push(position);
+        }
+        self.symbols.push(symbol);
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn label_name(&self, label: usize) -> Option<&str> {
+        self.labels.get(&label).map(|p| self.symbols[*p].name.as_str())
+    }
+
+    // all the signal names of a wire
+    pub fn wire_names(&self, wire: usize) -> Vec<&str> {
+        self.wires
+            .get(&wire)
+            .map(|positions| positions.iter().map(|p| self.symbols[*p].name.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    // a readable name of a wire, falling back to its index
+    pub fn wire_name(&self, wire: usize) -> String {
+        match self.wire_names(wire).as_slice() {
+            [] => format!("wire {}", wire),
+            [name] => name.to_string(),
+            names => names.join("|"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_reader() {
+        let sym = "1,1,0,main.out\n2,2,0,main.a\n3,-1,1,main.sub.tmp\n4,2,1,main.sub.in\n";
+        let symbols = CircuitSymbols::from_reader(sym.as_bytes()).unwrap();
+        assert_eq!(symbols.wire_name(1), "main.out");
+        assert_eq!(symbols.wire_name(2), "main.a|main.sub.in");
+        assert_eq!(symbols.wire_name(3), "wire 3");
+        assert_eq!(symbols.label_name(3), Some("main.sub.tmp"));
+        assert_eq!(symbols.symbols()[2].wire, None);
+        assert!(CircuitSymbols::from_reader("1,x,0,main.out".as_bytes()).is_err());
+    }
+}
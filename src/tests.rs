@@ -9,17 +9,18 @@ const WITNESS_FILE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/ci
 const VK_FILE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/circuits/simple/vk.bin");
 const PROOF_FILE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/circuits/simple/proof.bin");
 const MONOMIAL_KEY_FILE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/keys/setup/setup_2^10.key");
-const DEFAULT_TRANSCRIPT: &'static str = "keccak";
+const DEFAULT_TRANSCRIPT: plonk::Transcript = plonk::Transcript::Keccak;
 
 const CIRCUIT_ANALYZE_RESULT: &'static str = r#"{"num_inputs":2,"num_aux":2,"num_variables":4,"num_constraints":2,"num_nontrivial_constraints":2,"num_gates":3,"num_hints":2,"constraint_stats":[{"name":"0","num_gates":1},{"name":"1","num_gates":2}]}"#;
 
 #[test]
 fn test_analyze() {
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(CIRCUIT_FILE),
+        r1cs: reader::load_r1cs::<Bn256>(CIRCUIT_FILE).unwrap(),
         witness: None,
         wire_mapping: None,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
     };
 
     let result = crate::plonk::analyse(circuit).unwrap();
@@ -30,13 +31,14 @@ fn test_analyze() {
 #[test]
 fn test_export_verification_key() {
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(CIRCUIT_FILE),
+        r1cs: reader::load_r1cs::<Bn256>(CIRCUIT_FILE).unwrap(),
         witness: None,
         wire_mapping: None,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
     };
 
-    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit, reader::load_key_monomial_form(MONOMIAL_KEY_FILE), None)
+    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit, reader::load_key_monomial_form(MONOMIAL_KEY_FILE).unwrap(), None)
         .expect("prepare err");
     let vk = setup.make_verification_key().unwrap();
     let mut buf = vec![];
@@ -48,16 +50,17 @@ fn test_export_verification_key() {
 #[test]
 fn test_prove() {
     let circuit = CircomCircuit {
-        r1cs: reader::load_r1cs(CIRCUIT_FILE),
-        witness: Some(reader::load_witness_from_file::<Bn256>(WITNESS_FILE)),
+        r1cs: reader::load_r1cs::<Bn256>(CIRCUIT_FILE).unwrap(),
+        witness: Some(reader::load_witness_from_file::<Bn256>(WITNESS_FILE).unwrap()),
         wire_mapping: None,
         aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
     };
 
     let setup = plonk::SetupForProver::prepare_setup_for_prover(
         circuit.clone(),
-        reader::load_key_monomial_form(MONOMIAL_KEY_FILE),
-        reader::maybe_load_key_lagrange_form(None),
+        reader::load_key_monomial_form(MONOMIAL_KEY_FILE).unwrap(),
+        reader::maybe_load_key_lagrange_form(None).unwrap(),
     )
     .unwrap();
 
@@ -72,10 +75,312 @@ fn test_prove() {
     assert_eq!(check_proof, buf);
 }
 
+#[test]
+fn test_prove_rescue_with_lagrange_form() {
+    let circuit = CircomCircuit {
+        r1cs: reader::load_r1cs::<Bn256>(CIRCUIT_FILE).unwrap(),
+        witness: Some(reader::load_witness_from_file::<Bn256>(WITNESS_FILE).unwrap()),
+        wire_mapping: None,
+        aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
+    };
+
+    let key_monomial_form = reader::load_key_monomial_form(MONOMIAL_KEY_FILE).unwrap();
+    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit.clone(), key_monomial_form, None).unwrap();
+    let key_lagrange_form = setup.get_srs_lagrange_form_from_monomial_form();
+    let setup = plonk::SetupForProver::prepare_setup_for_prover(
+        circuit.clone(),
+        reader::load_key_monomial_form(MONOMIAL_KEY_FILE).unwrap(),
+        Some(key_lagrange_form),
+    )
+    .unwrap();
+
+    assert!(setup.prove(circuit, plonk::Transcript::Rescue).is_err());
+}
+
+#[test]
+fn test_prove_with_proving_key() {
+    let circuit = CircomCircuit {
+        r1cs: reader::load_r1cs::<Bn256>(CIRCUIT_FILE).unwrap(),
+        witness: Some(reader::load_witness_from_file::<Bn256>(WITNESS_FILE).unwrap()),
+        wire_mapping: None,
+        aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
+    };
+    let digest = circuit.r1cs.digest();
+
+    let mut buf = vec![];
+    plonk::ProvingKey::generate(circuit.clone(), digest)
+        .unwrap()
+        .write(&mut buf)
+        .unwrap();
+    let proving_key = plonk::ProvingKey::read(buf.as_slice()).unwrap();
+    assert_eq!(proving_key.circuit_digest, digest);
+
+    let setup = plonk::SetupForProver::prepare_setup_for_prover_from_proving_key(
+        proving_key,
+        reader::load_key_monomial_form(MONOMIAL_KEY_FILE).unwrap(),
+        None,
+    )
+    .unwrap();
+
+    let proof = setup.prove(circuit, DEFAULT_TRANSCRIPT).unwrap();
+    let mut buf = vec![];
+    proof.write(&mut buf).unwrap();
+    let check_proof = fs::read(PROOF_FILE).unwrap();
+    assert_eq!(check_proof, buf);
+}
+
 #[test]
 fn test_verify() {
-    let vk = reader::load_verification_key::<Bn256>(VK_FILE);
+    let vk = reader::load_verification_key::<Bn256>(VK_FILE).unwrap();
 
-    let proof = reader::load_proof::<Bn256>(PROOF_FILE);
+    let proof = reader::load_proof::<Bn256>(PROOF_FILE).unwrap();
     assert!(plonk::verify(&vk, &proof, DEFAULT_TRANSCRIPT).expect("fail to verify proof"));
 }
+
+#[test]
+fn test_load_artifact_meta() {
+    let dir = std::env::temp_dir().join(format!("plonkit_test_load_artifact_meta_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let proof_file = dir.join("proof.bin");
+    let proof_file = proof_file.to_str().unwrap();
+    fs::copy(PROOF_FILE, proof_file).unwrap();
+
+    // a proof without meta is loaded as it is
+    let (_, meta) = reader::load_proof_with_meta::<Bn256>(proof_file).unwrap();
+    assert_eq!(meta, None);
+
+    let meta = plonk::ArtifactMeta {
+        curve: crate::curve::Curve::Bn256,
+        transcript: Some(DEFAULT_TRANSCRIPT),
+        circuit_digest: [1; 32],
+        srs_power: 10,
+    };
+    let mut buf = vec![];
+    meta.write(&mut buf).unwrap();
+    fs::write(plonk::artifact_meta_file(proof_file), buf).unwrap();
+    let (proof, loaded) = reader::load_proof_with_meta::<Bn256>(proof_file).unwrap();
+    assert_eq!(loaded, Some(meta));
+
+    // the proof file itself is untouched by its meta
+    let mut buf = vec![];
+    proof.write(&mut buf).unwrap();
+    assert_eq!(fs::read(PROOF_FILE).unwrap(), buf);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_verify_batch() {
+    use crate::bellman_ce::Field;
+
+    let vk = reader::load_verification_key::<Bn256>(VK_FILE).unwrap();
+    let proof = reader::load_proof::<Bn256>(PROOF_FILE).unwrap();
+    let mut bad_proof = proof.clone();
+    bad_proof.input_values[0].add_assign(&Field::one());
+
+    let proofs = vec![proof.clone(), proof.clone()];
+    assert!(plonk::verify_batch(&vk, &proofs, DEFAULT_TRANSCRIPT).unwrap().is_empty());
+    let proofs = vec![proof.clone(), bad_proof, proof];
+    assert_eq!(plonk::verify_batch(&vk, &proofs, DEFAULT_TRANSCRIPT).unwrap(), vec![1]);
+}
+
+#[test]
+fn test_json_round_trip() {
+    use crate::bellman_ce::plonk::better_cs::{
+        cs::PlonkCsWidth4WithNextStepParams,
+        keys::{Proof, VerificationKey},
+    };
+    use crate::{ProofStr, VerificationKeyStr};
+
+    let vk = reader::load_verification_key::<Bn256>(VK_FILE).unwrap();
+    let json = serde_json::to_string(&VerificationKeyStr::from(&vk)).unwrap();
+    let decoded =
+        VerificationKey::<Bn256, PlonkCsWidth4WithNextStepParams>::try_from(serde_json::from_str::<VerificationKeyStr>(&json).unwrap())
+            .unwrap();
+    let (mut expected, mut actual) = (vec![], vec![]);
+    vk.write(&mut expected).unwrap();
+    decoded.write(&mut actual).unwrap();
+    assert_eq!(expected, actual);
+
+    let proof = reader::load_proof::<Bn256>(PROOF_FILE).unwrap();
+    let json = serde_json::to_string(&ProofStr::from(&proof)).unwrap();
+    let decoded = Proof::<Bn256, PlonkCsWidth4WithNextStepParams>::try_from(serde_json::from_str::<ProofStr>(&json).unwrap()).unwrap();
+    let (mut expected, mut actual) = (vec![], vec![]);
+    proof.write(&mut expected).unwrap();
+    decoded.write(&mut actual).unwrap();
+    assert_eq!(expected, actual);
+
+    // a point off the curve is rejected
+    let mut proof_str = ProofStr::from(&proof);
+    proof_str.opening_at_z_proof = proof_str.grand_product_commitment.replacen(|c: char| c != '0', "0", 1);
+    assert!(Proof::<Bn256, PlonkCsWidth4WithNextStepParams>::try_from(proof_str).is_err());
+}
+
+#[test]
+fn test_groth16() {
+    use crate::bellman_ce::Field;
+    use crate::groth16;
+
+    let r1cs = reader::load_r1cs::<Bn256>(CIRCUIT_FILE).unwrap();
+    let digest = r1cs.digest();
+    let circuit = CircomCircuit {
+        r1cs,
+        witness: Some(reader::load_witness_from_file::<Bn256>(WITNESS_FILE).unwrap()),
+        wire_mapping: None,
+        aux_offset: groth16::AUX_OFFSET,
+        symbols: None,
+    };
+    let params = groth16::ProvingParameters::generate(circuit.clone(), digest).unwrap();
+    let mut buf = vec![];
+    params.write(&mut buf).unwrap();
+    assert_eq!(groth16::read_curve(&buf[..]).unwrap(), crate::curve::Curve::Bn256);
+    assert!(groth16::ProvingParameters::<crate::bellman_ce::bls12_381::Bls12>::read(&buf[..]).is_err());
+    let params = groth16::ProvingParameters::read(&buf[..]).unwrap();
+    assert_eq!(params.circuit_digest, digest);
+
+    let mut public_inputs = circuit.get_public_inputs().unwrap();
+    let proof = groth16::prove(circuit, &params).unwrap();
+    assert!(groth16::verify(params.verification_key(), &proof, &public_inputs).unwrap());
+    public_inputs[0].add_assign(&Field::one());
+    assert!(!groth16::verify(params.verification_key(), &proof, &public_inputs).unwrap());
+}
+
+#[test]
+fn test_bls12_381() {
+    use crate::bellman_ce::bls12_381::Bls12;
+
+    // json circuits don't record their prime, the small coefficients of this one are the same on both curves
+    let circuit = CircomCircuit {
+        r1cs: reader::load_r1cs::<Bls12>(CIRCUIT_FILE).unwrap(),
+        witness: Some(reader::load_witness_from_file::<Bls12>(WITNESS_FILE).unwrap()),
+        wire_mapping: None,
+        aux_offset: plonk::AUX_OFFSET,
+        symbols: None,
+    };
+    let setup = plonk::SetupForProver::prepare_setup_for_prover(circuit.clone(), plonk::gen_key_monomial_form(10).unwrap(), None).unwrap();
+    let vk = setup.make_verification_key().unwrap();
+    assert!(setup.prove(circuit.clone(), plonk::Transcript::Rescue).is_err());
+    let proof = setup.prove(circuit, DEFAULT_TRANSCRIPT).unwrap();
+    assert!(plonk::verify(&vk, &proof, DEFAULT_TRANSCRIPT).unwrap());
+}
+
+#[test]
+fn test_server() {
+    use crate::server::{Prover, ProverOptions};
+
+    // serve copies of the circuit from a directory of their own, as two circuits
+    let dir = std::env::temp_dir().join(format!("plonkit_test_server_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (from, to) in [
+        (CIRCUIT_FILE, "a.r1cs.json"),
+        (CIRCUIT_FILE, "b.r1cs.json"),
+        (WITNESS_FILE, "witness.json"),
+        (VK_FILE, "vk.bin"),
+    ] {
+        fs::copy(from, dir.join(to)).unwrap();
+    }
+    let options = ProverOptions {
+        root: dir.clone(),
+        max_cached: 1,
+    };
+    let prover = Prover::<Bn256>::new(reader::load_key_monomial_form(MONOMIAL_KEY_FILE).unwrap(), options).unwrap();
+    let prove = r#"{"circuit": "a.r1cs.json", "witness": "witness.json"}"#;
+    let (status, proof) = prover.handle("POST", "/prove", prove.as_bytes());
+    assert_eq!(status, 200, "{}", proof);
+    // the second proof reuses the cached setup
+    let (status, _) = prover.handle("POST", "/prove", prove.as_bytes());
+    assert_eq!(status, 200);
+    assert_eq!(prover.num_cached(), 1);
+    // the setup of another circuit takes the place of the first one
+    let prove_other = r#"{"circuit": "b.r1cs.json", "witness": "witness.json"}"#;
+    assert_eq!(prover.handle("POST", "/prove", prove_other.as_bytes()).0, 200);
+    assert_eq!(prover.num_cached(), 1);
+
+    let verify = format!(r#"{{"verification_key": "vk.bin", "proof": {}}}"#, proof);
+    let (status, result) = prover.handle("POST", "/verify", verify.as_bytes());
+    assert_eq!(status, 200, "{}", result);
+    assert_eq!(result, r#"{"valid":true,"transcript":"keccak"}"#);
+
+    assert_eq!(prover.handle("POST", "/prove", b"{}").0, 400);
+    let missing_witness = r#"{"circuit": "a.r1cs.json"}"#;
+    assert_eq!(prover.handle("POST", "/prove", missing_witness.as_bytes()).0, 422);
+    let outside = format!(r#"{{"circuit": "{}", "witness": "witness.json"}}"#, CIRCUIT_FILE);
+    let (status, result) = prover.handle("POST", "/prove", outside.as_bytes());
+    assert_eq!(status, 422);
+    assert!(result.contains("outside of the served directory"), "{}", result);
+    assert_eq!(prover.handle("GET", "/prove", b"").0, 404);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_vk_tree() {
+    use crate::recursive;
+
+    let vk = reader::load_verification_key::<Bn256>(VK_FILE).unwrap();
+    let proof = reader::load_proof::<Bn256>(PROOF_FILE).unwrap();
+
+    // the leaves past the given vks repeat the last one
+    let depth = recursive::DEFAULT_VK_TREE_DEPTH;
+    let root = recursive::get_vk_tree_root_hash(&[vk.clone()], depth).unwrap();
+    assert_eq!(recursive::get_vk_tree_root_hash(&[vk.clone(), vk.clone()], depth).unwrap(), root);
+    assert_ne!(recursive::get_vk_tree_root_hash(&[vk.clone()], 2).unwrap(), root);
+    assert!(recursive::get_vk_tree_root_hash(&[], depth).is_err());
+    // a tree of depth 1 holds 2 vks
+    assert!(recursive::get_vk_tree_root_hash(&[vk.clone(), vk.clone(), vk.clone()], 1).is_err());
+
+    let vks = vec![vk.clone(), vk];
+    assert!(recursive::get_aggregated_input(vec![proof.clone()], &vks, &[2], depth).is_err());
+    assert!(recursive::get_aggregated_input(vec![proof], &vks, &[0, 1], depth).is_err());
+}
+
+// takes minutes to synthesize the recursive circuit, run with `cargo test -- --ignored`
+#[test]
+#[ignore]
+fn test_recursive_proving_key_setup() {
+    use crate::recursive::{RecursiveMeta, RecursiveProvingKey};
+    use recurisive_vk_codegen::circuit::create_recursive_circuit_setup;
+
+    // the smallest recursive circuit, checking a single proof of one input with a vk tree of depth 1
+    let meta = RecursiveMeta {
+        vk_tree_depth: 1,
+        num_proofs_to_check: 1,
+        num_inputs: 1,
+    };
+    let proving_key = RecursiveProvingKey {
+        meta,
+        setup: create_recursive_circuit_setup(meta.num_proofs_to_check, meta.num_inputs, meta.vk_tree_depth).unwrap(),
+    };
+    let mut buf = vec![];
+    proving_key.write(&mut buf).unwrap();
+    let read = RecursiveProvingKey::read(&buf[..]).unwrap();
+    assert_eq!(read.meta, meta);
+    let mut reread = vec![];
+    read.write(&mut reread).unwrap();
+    assert_eq!(reread, buf);
+}
+
+#[test]
+fn test_pad_recursive_batch() {
+    use crate::recursive::{self, Padding};
+
+    let proof = reader::load_proof::<Bn256>(PROOF_FILE).unwrap();
+    let padding = Padding {
+        proof: proof.clone(),
+        vk_index: 1,
+    };
+    let (mut proofs, mut vk_indexes) = (vec![proof.clone()], vec![0]);
+    recursive::pad(&mut proofs, &mut vk_indexes, 3, Some(&padding)).unwrap();
+    assert_eq!(proofs.len(), 3);
+    assert_eq!(vk_indexes, vec![0, 1, 1]);
+
+    let (mut proofs, mut vk_indexes) = (vec![proof.clone()], vec![0]);
+    assert!(recursive::pad(&mut proofs, &mut vk_indexes, 2, None).is_err());
+    recursive::pad(&mut proofs, &mut vk_indexes, 1, None).unwrap();
+    assert!(recursive::pad(&mut vec![proof.clone(), proof.clone()], &mut vec![0, 0], 1, Some(&padding)).is_err());
+
+    // a short vk index list is not filled up with the padding's
+    let (mut proofs, mut vk_indexes) = (vec![proof.clone(), proof], vec![0]);
+    assert!(recursive::pad(&mut proofs, &mut vk_indexes, 3, Some(&padding)).is_err());
+    assert_eq!(vk_indexes, vec![0]);
+}
@@ -0,0 +1,204 @@
+// detect signals which the constraints don't pin down, a common soundness bug of circom circuits.
+// wires are laid out as: the constant one, outputs, public inputs, private inputs, then intermediate signals
+use crate::bellman_ce::pairing::{ff::Field, Engine};
+use crate::circom_circuit::R1CS;
+use crate::symbols::CircuitSymbols;
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignalReport {
+    pub wire: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnderconstrainedReport {
+    // private signals which appear in no constraint, so they can take any value
+    pub unused_signals: Vec<SignalReport>,
+    // intermediate signals which only appear linearly, and whose value doesn't follow from the inputs through the
+    // constraints, so they can take any value
+    pub linear_only_signals: Vec<SignalReport>,
+    // public inputs which appear in no constraint, so a proof is valid for any value of them
+    pub unconstrained_public_inputs: Vec<SignalReport>,
+    // outputs which aren't connected to any input through the constraints
+    pub outputs_independent_of_inputs: Vec<SignalReport>,
+}
+
+impl UnderconstrainedReport {
+    pub fn num_findings(&self) -> usize {
+        self.unused_signals.len()
+            + self.linear_only_signals.len()
+            + self.unconstrained_public_inputs.len()
+            + self.outputs_independent_of_inputs.len()
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Occurrence {
+    num_constraints: usize,
+    nonlinear: bool,
+}
+
+fn find(parents: &mut [usize], mut wire: usize) -> usize {
+    while parents[wire] != wire {
+        parents[wire] = parents[parents[wire]];
+        wire = parents[wire];
+    }
+    wire
+}
+
+// an lc is constant if it only refers to the wire of constant one
+fn is_constant<E: Engine>(lc: &[(usize, E::Fr)]) -> bool {
+    lc.iter().all(|(wire, coeff)| *wire == 0 || coeff.is_zero())
+}
+
+pub fn analyse<E: Engine>(r1cs: &R1CS<E>, symbols: Option<&CircuitSymbols>) -> UnderconstrainedReport {
+    let num_wires = r1cs.num_variables;
+    let outputs = 1..1 + r1cs.num_outputs;
+    let public_inputs = outputs.end..r1cs.num_inputs;
+    let private_inputs = r1cs.num_inputs..(r1cs.num_inputs + r1cs.num_prv_inputs).min(num_wires);
+    let intermediates = private_inputs.end..num_wires;
+
+    let mut occurrences = vec![Occurrence::default(); num_wires];
+    // connected components of the graph linking the wires of each constraint
+    let mut parents: Vec<usize> = (0..num_wires).collect();
+    // the wires of each constraint, and whether the constraint can determine them, which it can't if they are squared
+    let mut constraint_wires: Vec<Vec<(usize, bool)>> = Vec::with_capacity(r1cs.constraints.len());
+    let in_lc = |lc: &[(usize, E::Fr)], wire: usize| lc.iter().any(|(w, coeff)| *w == wire && !coeff.is_zero());
+    for (a, b, c) in &r1cs.constraints {
+        // a product of two non constant lcs makes the wires in them appear non linearly
+        let product = !is_constant::<E>(a) && !is_constant::<E>(b);
+        let mut wires: Vec<(usize, bool)> = Vec::new();
+        for (lc, in_product) in &[(a, product), (b, product), (c, false)] {
+            for (wire, coeff) in lc.iter() {
+                if *wire != 0 && !coeff.is_zero() {
+                    wires.push((*wire, *in_product));
+                }
+            }
+        }
+        wires.sort_unstable();
+        wires.dedup_by(|next, prev| {
+            let same = next.0 == prev.0;
+            prev.1 |= same && next.1;
+            same
+        });
+        for (wire, nonlinear) in &wires {
+            occurrences[*wire].num_constraints += 1;
+            occurrences[*wire].nonlinear |= *nonlinear;
+        }
+        for pair in wires.windows(2) {
+            let (x, y) = (find(&mut parents, pair[0].0), find(&mut parents, pair[1].0));
+            parents[x] = y;
+        }
+        constraint_wires.push(
+            wires
+                .iter()
+                .map(|(wire, _)| (*wire, !(product && in_lc(a, *wire) && in_lc(b, *wire))))
+                .collect(),
+        );
+    }
+
+    // propagate which wires follow from the inputs: a constraint with a single undetermined wire determines it
+    let mut constraints_of = vec![Vec::new(); num_wires];
+    for (i, wires) in constraint_wires.iter().enumerate() {
+        for (wire, _) in wires {
+            constraints_of[*wire].push(i);
+        }
+    }
+    let mut determined = vec![false; num_wires];
+    let mut num_undetermined: Vec<usize> = constraint_wires.iter().map(Vec::len).collect();
+    let mut single: Vec<usize> = (0..constraint_wires.len()).filter(|i| num_undetermined[*i] == 1).collect();
+    let mut newly_determined: Vec<usize> = public_inputs.clone().chain(private_inputs.clone()).collect();
+    loop {
+        while let Some(wire) = newly_determined.pop() {
+            if determined[wire] {
+                continue;
+            }
+            determined[wire] = true;
+            for i in &constraints_of[wire] {
+                num_undetermined[*i] -= 1;
+                if num_undetermined[*i] == 1 {
+                    single.push(*i);
+                }
+            }
+        }
+        match single.pop() {
+            Some(i) => {
+                if let Some((wire, true)) = constraint_wires[i].iter().find(|(wire, _)| !determined[*wire]) {
+                    newly_determined.push(*wire);
+                }
+            }
+            None => break,
+        }
+    }
+
+    let report = |wire: usize| SignalReport {
+        wire,
+        name: symbols.map(|s| s.wire_name(wire)),
+    };
+    let unused = |wire: &usize| occurrences[*wire].num_constraints == 0;
+
+    let input_roots: Vec<usize> = public_inputs
+        .clone()
+        .chain(private_inputs.clone())
+        .map(|wire| find(&mut parents, wire))
+        .collect();
+    let independent_outputs: Vec<usize> = outputs
+        .clone()
+        .filter(|wire| {
+            let root = find(&mut parents, *wire);
+            !input_roots.contains(&root)
+        })
+        .collect();
+
+    UnderconstrainedReport {
+        unused_signals: private_inputs.chain(intermediates.clone()).filter(unused).map(report).collect(),
+        linear_only_signals: intermediates
+            .filter(|wire| occurrences[*wire].num_constraints > 0 && !occurrences[*wire].nonlinear && !determined[*wire])
+            .map(report)
+            .collect(),
+        unconstrained_public_inputs: public_inputs.filter(unused).map(report).collect(),
+        outputs_independent_of_inputs: independent_outputs.into_iter().map(report).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::{
+        bn256::{Bn256, Fr},
+        ff::PrimeField,
+    };
+
+    #[test]
+    fn test_analyse() {
+        let one = || Fr::from_str("1").unwrap();
+        // wires: 1 out, 2 public in, 3 private in, 4 unused private in, 5 and 6 intermediates, 7 and 8 free intermediates
+        let r1cs = R1CS::<Bn256> {
+            num_inputs: 3,
+            num_outputs: 1,
+            num_prv_inputs: 2,
+            num_aux: 6,
+            num_variables: 9,
+            constraints: vec![
+                // w5 = w3 * w3
+                (vec![(3, one())], vec![(3, one())], vec![(5, one())]),
+                // w1 = w5 * w3
+                (vec![(5, one())], vec![(3, one())], vec![(1, one())]),
+                // w6 = w5 + 1
+                (vec![(0, one())], vec![(5, one()), (0, one())], vec![(6, one())]),
+                // w3 = w7 + w8
+                (vec![(0, one())], vec![(7, one()), (8, one())], vec![(3, one())]),
+            ],
+            custom_gates: vec![],
+            custom_gates_applied: vec![],
+        };
+        let wires = |signals: &[SignalReport]| signals.iter().map(|s| s.wire).collect::<Vec<_>>();
+        let report = analyse(&r1cs, None);
+        assert_eq!(wires(&report.unused_signals), vec![4]);
+        assert_eq!(wires(&report.linear_only_signals), vec![7, 8]);
+        assert_eq!(wires(&report.unconstrained_public_inputs), vec![2]);
+        assert!(report.outputs_independent_of_inputs.is_empty());
+        assert_eq!(report.num_findings(), 4);
+    }
+}
@@ -3,6 +3,9 @@ use franklin_crypto::plonk::circuit::bigint::field::RnsParameters;
 pub use num_bigint::BigUint;
 use num_traits::Num;
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
 //export some more funcs
 pub use franklin_crypto::plonk::circuit::bigint::bigint::{biguint_to_fe, fe_to_biguint};
@@ -117,6 +120,31 @@ pub fn witness_to_field<E: Engine, F: PrimeField>(limbs: &[BigUint], params: &Rn
     }
 }
 
+/// write a file through a temporary file next to it, which is renamed over it once complete, so that a failed or
+/// interrupted write never leaves a truncated file behind
+pub fn write_file_atomically<F: FnOnce(&Path) -> std::io::Result<()>>(path: &str, write: F) -> std::io::Result<()> {
+    let target = Path::new(path);
+    let name = target
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not a file path", path)))?;
+    let tmp = target.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+    let result = write(&tmp).and_then(|_| std::fs::rename(&tmp, target));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// write a file atomically with a buffered writer, see `write_file_atomically`
+pub fn write_atomically<F: FnOnce(&mut BufWriter<File>) -> std::io::Result<()>>(path: &str, write: F) -> std::io::Result<()> {
+    write_file_atomically(path, |tmp| {
+        let mut writer = BufWriter::new(File::create(tmp)?);
+        write(&mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +177,25 @@ mod tests {
         let fq_restored = witness_to_field(&wts[..], &rns_params);
         assert_eq!(fq, fq_restored);
     }
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = std::env::temp_dir().join(format!("plonkit_test_write_atomically_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.bin");
+        let path = path.to_str().unwrap();
+
+        write_atomically(path, |w| w.write_all(b"complete")).unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"complete");
+
+        // a failed write leaves the previous file and no temporary file
+        let failed = write_atomically(path, |w| {
+            w.write_all(b"trunc")?;
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "interrupted"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read(path).unwrap(), b"complete");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
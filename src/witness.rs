@@ -0,0 +1,368 @@
+// a port of circom's generated `witness_calculator.js`, running `circuit.wasm` in an embedded runtime
+use anyhow::{bail, Context};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Num, Zero};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+use wasmer::{imports, Function, Instance, LazyInit, Module, NativeFunc, RuntimeError, Store, WasmerEnv};
+
+use crate::bellman_ce::{
+    pairing::{ff::PrimeField, Engine},
+    Field, PrimeFieldRepr,
+};
+use crate::utils::repr_to_big;
+
+// the error raised by circom's `exceptionHandler` import, which aborts the wasm execution
+#[derive(Debug)]
+struct CircomException {
+    code: i32,
+    // what the circuit reported with `printErrorMessage` before failing
+    message: String,
+}
+
+impl std::fmt::Display for CircomException {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.code {
+            1 => "signal not found",
+            2 => "too many signals set",
+            3 => "signal already set",
+            4 => "assert failed",
+            5 => "not enough memory",
+            6 => "input signal array access exceeds the size",
+            _ => "unknown error",
+        };
+        write!(f, "circom runtime exception {}: {}", self.code, reason)?;
+        if !self.message.is_empty() {
+            write!(f, "\n{}", self.message.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CircomException {}
+
+// what the circom runtime imports need of the instance calling them
+#[derive(WasmerEnv, Clone, Default)]
+struct RuntimeEnv {
+    // missing in the wasm of early circom 2.0 versions, which print no messages
+    #[wasmer(export(name = "getMessageChar", optional = true))]
+    get_message_char: LazyInit<NativeFunc<(), i32>>,
+    #[wasmer(export(name = "getFieldNumLen32"))]
+    get_field_num_len32: LazyInit<NativeFunc<(), i32>>,
+    #[wasmer(export(name = "readSharedRWMemory"))]
+    read_shared_rw_memory: LazyInit<NativeFunc<i32, i32>>,
+    // the error messages printed since the last exception
+    errors: Arc<Mutex<String>>,
+    // the `log` output of the circuit until its line ends
+    message: Arc<Mutex<String>>,
+}
+
+impl RuntimeEnv {
+    // read the message circom wrote to its buffer, up to the terminating zero
+    fn read_message(&self) -> Result<String, RuntimeError> {
+        let mut message = String::new();
+        if let Some(get_message_char) = self.get_message_char_ref() {
+            loop {
+                let c = get_message_char.call()?;
+                if c == 0 {
+                    break;
+                }
+                message.push(c as u8 as char);
+            }
+        }
+        Ok(message)
+    }
+}
+
+fn exception_handler(env: &RuntimeEnv, code: i32) -> Result<(), CircomException> {
+    let message = std::mem::take(&mut *env.errors.lock().unwrap());
+    Err(CircomException { code, message })
+}
+
+fn print_error_message(env: &RuntimeEnv) -> Result<(), RuntimeError> {
+    let message = env.read_message()?;
+    log::error!("circom runtime error: {}", message);
+    let mut errors = env.errors.lock().unwrap();
+    errors.push_str(&message);
+    errors.push('\n');
+    Ok(())
+}
+
+fn write_buffer_message(env: &RuntimeEnv) -> Result<(), RuntimeError> {
+    let message = env.read_message()?;
+    let mut buffer = env.message.lock().unwrap();
+    if message == "\n" {
+        log::info!("{}", buffer);
+        buffer.clear();
+    } else {
+        if !buffer.is_empty() {
+            buffer.push(' ');
+        }
+        buffer.push_str(&message);
+    }
+    Ok(())
+}
+
+// append the field element in the shared memory to the `log` output, such as the values logged by the circuit
+fn show_shared_rw_memory(env: &RuntimeEnv) -> Result<(), RuntimeError> {
+    let (get_field_num_len32, read) = match (env.get_field_num_len32_ref(), env.read_shared_rw_memory_ref()) {
+        (Some(get_field_num_len32), Some(read)) => (get_field_num_len32, read),
+        _ => return Ok(()),
+    };
+    let value = read_field_element(read, get_field_num_len32.call()? as usize)?;
+    let mut buffer = env.message.lock().unwrap();
+    if !buffer.is_empty() {
+        buffer.push(' ');
+    }
+    buffer.push_str(&value.to_string());
+    Ok(())
+}
+
+// read a field element from circom's shared memory, which holds it in little endian u32 limbs
+fn read_field_element(read: &NativeFunc<i32, i32>, n32: usize) -> Result<BigUint, RuntimeError> {
+    let mut limbs = Vec::with_capacity(n32);
+    for j in 0..n32 {
+        limbs.push(read.call(j as i32)? as u32);
+    }
+    Ok(BigUint::from_slice(&limbs))
+}
+
+pub struct WitnessCalculator {
+    instance: Instance,
+    n32: usize,
+    prime: BigUint,
+}
+
+impl WitnessCalculator {
+    // load a circom 2.x `circuit.wasm`
+    pub fn from_file(filename: &str) -> Result<Self, anyhow::Error> {
+        let store = Store::default();
+        let module = Module::from_file(&store, filename).map_err(|e| invalid_input!("failed to load wasm file {}: {}", filename, e))?;
+        let env = RuntimeEnv::default();
+        let import_object = imports! {
+            "runtime" => {
+                "exceptionHandler" => Function::new_native_with_env(&store, env.clone(), exception_handler),
+                "showSharedRWMemory" => Function::new_native_with_env(&store, env.clone(), show_shared_rw_memory),
+                "printErrorMessage" => Function::new_native_with_env(&store, env.clone(), print_error_message),
+                "writeBufferMessage" => Function::new_native_with_env(&store, env, write_buffer_message),
+            }
+        };
+        let instance = Instance::new(&module, &import_object)?;
+
+        if instance.exports.get_function("getVersion").is_err() {
+            bail!(invalid_input!(
+                "{} is not generated by circom 2.x, which is the only supported version",
+                filename
+            ));
+        }
+        let version = instance.exports.get_native_function::<(), i32>("getVersion")?.call()?;
+        log::info!("circom wasm version {}", version);
+
+        let n32 = instance.exports.get_native_function::<(), i32>("getFieldNumLen32")?.call()? as usize;
+        instance.exports.get_native_function::<(), ()>("getRawPrime")?.call()?;
+        let mut calculator = WitnessCalculator {
+            instance,
+            n32,
+            prime: BigUint::zero(),
+        };
+        calculator.prime = calculator.read_shared_rw_memory()?;
+        Ok(calculator)
+    }
+
+    fn read_shared_rw_memory(&self) -> Result<BigUint, anyhow::Error> {
+        let read = self.instance.exports.get_native_function::<i32, i32>("readSharedRWMemory")?;
+        Ok(read_field_element(&read, self.n32)?)
+    }
+
+    fn write_shared_rw_memory(&self, value: &BigUint) -> Result<(), anyhow::Error> {
+        let write = self.instance.exports.get_native_function::<(i32, i32), ()>("writeSharedRWMemory")?;
+        let mut limbs = value.to_u32_digits();
+        limbs.resize(self.n32, 0);
+        for (j, limb) in limbs.into_iter().enumerate() {
+            write.call(j as i32, limb as i32)?;
+        }
+        Ok(())
+    }
+
+    // reduce an input value into the field, the same way as circom's `normalize`
+    fn normalize(&self, value: &BigInt) -> BigUint {
+        let prime = BigInt::from_biguint(Sign::Plus, self.prime.clone());
+        let value = ((value % &prime) + &prime) % &prime;
+        value.to_biguint().expect("normalized value must be positive")
+    }
+
+    // calculate the full witness vector from the circuit inputs
+    pub fn calculate_witness<E: Engine>(
+        &self,
+        inputs: &BTreeMap<String, Vec<BigInt>>,
+        sanity_check: bool,
+    ) -> Result<Vec<E::Fr>, anyhow::Error> {
+        let field_prime = BigUint::from_str_radix(&repr_to_big(E::Fr::char()), 10)?;
+        if self.prime != field_prime {
+            bail!(invalid_input!(
+                "the wasm circuit is compiled for prime {}, which does not match the engine",
+                self.prime
+            ));
+        }
+        let num_bytes = E::Fr::zero().into_repr().as_ref().len() * 8;
+        if self.n32 * 4 != num_bytes {
+            bail!(invalid_input!(
+                "the wasm circuit uses {} byte field elements, expected {}",
+                self.n32 * 4,
+                num_bytes
+            ));
+        }
+
+        let exports = &self.instance.exports;
+        exports.get_native_function::<i32, ()>("init")?.call(sanity_check as i32)?;
+
+        let get_input_signal_size: NativeFunc<(i32, i32), i32> = exports.get_native_function("getInputSignalSize")?;
+        let set_input_signal: NativeFunc<(i32, i32, i32), ()> = exports.get_native_function("setInputSignal")?;
+        let mut input_counter = 0;
+        for (name, values) in inputs {
+            let hash = fnv_hash(name);
+            let (msb, lsb) = ((hash >> 32) as i32, (hash & 0xffff_ffff) as i32);
+            let signal_size = get_input_signal_size.call(msb, lsb)?;
+            if signal_size < 0 {
+                bail!(invalid_input!("signal {} not found", name));
+            }
+            if values.len() != signal_size as usize {
+                bail!(invalid_input!(
+                    "signal {} expects {} values, but {} are given",
+                    name,
+                    signal_size,
+                    values.len()
+                ));
+            }
+            for (i, value) in values.iter().enumerate() {
+                self.write_shared_rw_memory(&self.normalize(value))?;
+                set_input_signal
+                    .call(msb, lsb, i as i32)
+                    .map_err(|e| invalid_input!("failed to set signal {}[{}]: {}", name, i, e))?;
+                input_counter += 1;
+            }
+        }
+        // the wasm of early circom 2.0 versions doesn't tell its number of inputs
+        if let Ok(get_input_size) = exports.get_native_function::<(), i32>("getInputSize") {
+            let input_size = get_input_size.call()?;
+            if input_counter < input_size {
+                bail!(invalid_input!(
+                    "Not all inputs have been set. Only {} out of {}",
+                    input_counter,
+                    input_size
+                ));
+            }
+        }
+
+        let witness_size = exports.get_native_function::<(), i32>("getWitnessSize")?.call()?;
+        let get_witness: NativeFunc<i32, ()> = exports.get_native_function("getWitness")?;
+        let mut result = Vec::with_capacity(witness_size as usize);
+        for i in 0..witness_size {
+            get_witness.call(i)?;
+            let value = self.read_shared_rw_memory()?;
+            let mut bytes = value.to_bytes_le();
+            bytes.resize(num_bytes, 0);
+            let mut repr = E::Fr::zero().into_repr();
+            repr.read_le(&bytes[..])?;
+            result.push(E::Fr::from_repr(repr)?);
+        }
+        Ok(result)
+    }
+}
+
+// 64-bit FNV-1a, which circom uses to look up input signals by name
+fn fnv_hash(name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn parse_input_value(value: &serde_json::Value, out: &mut Vec<BigInt>) -> Result<(), anyhow::Error> {
+    match value {
+        serde_json::Value::Array(values) => {
+            for v in values {
+                parse_input_value(v, out)?;
+            }
+        }
+        serde_json::Value::Number(n) => out.push(BigInt::from_str_radix(&n.to_string(), 10)?),
+        serde_json::Value::String(s) => {
+            let parsed = match s.strip_prefix("0x") {
+                Some(hex) => BigInt::from_str_radix(hex, 16),
+                None => BigInt::from_str_radix(s, 10),
+            };
+            out.push(parsed.map_err(|e| invalid_input!("invalid input value {}: {}", s, e))?);
+        }
+        _ => bail!(invalid_input!("invalid input value {}", value)),
+    }
+    Ok(())
+}
+
+/// load circuit inputs from a json file, flattening multi-dimensional signals
+pub fn load_inputs_from_json_file(filename: &str) -> Result<BTreeMap<String, Vec<BigInt>>, anyhow::Error> {
+    let file = File::open(filename).with_context(|| format!("failed to open input file {}", filename))?;
+    let json: BTreeMap<String, serde_json::Value> = serde_json::from_reader(BufReader::new(file))?;
+    parse_inputs(json)
+}
+
+/// parse circuit inputs from a json object, flattening multi-dimensional signals
+pub fn parse_inputs(json: BTreeMap<String, serde_json::Value>) -> Result<BTreeMap<String, Vec<BigInt>>, anyhow::Error> {
+    let mut inputs = BTreeMap::new();
+    for (name, value) in json {
+        let mut values = Vec::new();
+        parse_input_value(&value, &mut values)?;
+        inputs.insert(name, values);
+    }
+    Ok(inputs)
+}
+
+/// calculate a witness by running a circom wasm file on a json input file
+pub fn calculate_witness_from_files<E: Engine>(wasm_filename: &str, input_filename: &str) -> Result<Vec<E::Fr>, anyhow::Error> {
+    let inputs = load_inputs_from_json_file(input_filename)?;
+    let calculator = WitnessCalculator::from_file(wasm_filename)?;
+    calculator.calculate_witness::<E>(&inputs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::bn256::{Bn256, Fr};
+
+    // compiled with `circom circuit.circom --wasm`, which needs circom 2.x
+    const WASM_FILE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/circuits/simple/circuit_js/circuit.wasm");
+    const INPUT_FILE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/circuits/simple/input.json");
+
+    #[test]
+    fn test_calculate_witness() {
+        let calculator = WitnessCalculator::from_file(WASM_FILE).unwrap();
+        let inputs = load_inputs_from_json_file(INPUT_FILE).unwrap();
+        let witness = calculator.calculate_witness::<Bn256>(&inputs, true).unwrap();
+        // the constant one, the output c, then the inputs a and b
+        let expected: Vec<Fr> = ["1", "33", "1", "33"].iter().map(|x| Fr::from_str(x).unwrap()).collect();
+        assert_eq!(witness, expected);
+
+        let mut missing = inputs;
+        missing.remove("b");
+        let err = calculator.calculate_witness::<Bn256>(&missing, true).unwrap_err();
+        assert!(err.is::<crate::InvalidInput>());
+    }
+
+    #[test]
+    fn test_fnv_hash() {
+        // FNV-1a reference test vectors
+        assert_eq!(fnv_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv_hash("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_parse_input_value() {
+        let value: serde_json::Value = serde_json::from_str(r#"[["1", 2], ["0x10", "-3"]]"#).unwrap();
+        let mut out = Vec::new();
+        parse_input_value(&value, &mut out).unwrap();
+        assert_eq!(out, vec![BigInt::from(1), BigInt::from(2), BigInt::from(16), BigInt::from(-3)]);
+    }
+}